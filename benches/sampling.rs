@@ -0,0 +1,147 @@
+//! Error against samples per pixel for each sampler, compared with plain
+//! random sampling. Run with `cargo bench --bench sampling`.
+
+use std::{sync::Arc, time::Instant};
+
+use raytracer::{
+    bvh::Bvh,
+    camera::Camera,
+    framebuffer::Framebuffer,
+    hittable::HittableList,
+    material::{Dielectric, Lambertian, Metal},
+    render::Renderer,
+    sampler::{Sampler, SamplerKind},
+    settings::RenderSettings,
+    sphere::Sphere,
+    vec3::{Colour, Point, Vec3},
+};
+
+const KINDS: [SamplerKind; 5] = [
+    SamplerKind::Random,
+    SamplerKind::Stratified,
+    SamplerKind::Halton,
+    SamplerKind::Sobol,
+    SamplerKind::BlueNoise,
+];
+
+const SPP: [i64; 6] = [1, 4, 16, 64, 256, 1024];
+
+fn main() {
+    integrand();
+    println!();
+    render();
+}
+
+/// Estimates the mean of a smooth function and of a disc's edge over the
+/// unit square in many pixels, where the exact answers are known.
+fn integrand() {
+    let smooth = |(u, v): (f64, f64)| (u * v).sin() + u * u;
+    let smooth_exact = 0.239_811_742_000_564_73 + 1. / 3.;
+    let disc = |(u, v): (f64, f64)| ((u * u + v * v) < 1.) as u8 as f64;
+    let disc_exact = std::f64::consts::FRAC_PI_4;
+
+    type Integrand<'a> = (&'a str, &'a dyn Fn((f64, f64)) -> f64, f64);
+    let integrands: [Integrand; 2] = [
+        ("sin(uv) + u²", &smooth, smooth_exact),
+        ("quarter disc", &disc, disc_exact),
+    ];
+
+    for (name, f, exact) in integrands {
+        println!("RMS error integrating {name} over 1024 pixels");
+        header();
+        for kind in KINDS {
+            print!("{:<12}", format!("{kind:?}"));
+            for spp in SPP {
+                let mut sampler = Sampler::new(kind, spp as u64, 1);
+                let pixels = 1024;
+                let sum: f64 = (0..pixels)
+                    .map(|x| {
+                        let mean = (0..spp as u64)
+                            .map(|i| {
+                                sampler.start_sample(x % 64, x / 64, i);
+                                f(sampler.get_2d())
+                            })
+                            .sum::<f64>()
+                            / spp as f64;
+                        (mean - exact).powi(2)
+                    })
+                    .sum();
+                print!("{:>11.2e}", (sum / pixels as f64).sqrt());
+            }
+            println!();
+        }
+        println!();
+    }
+}
+
+/// Renders a small scene with depth of field, a glossy metal and glass, and
+/// compares with a render at many more samples.
+fn render() {
+    let mut world = HittableList::new();
+    let ground = Arc::new(Lambertian::new(&Colour::new(0.5, 0.5, 0.5)));
+    let diffuse = Arc::new(Lambertian::new(&Colour::new(0.1, 0.2, 0.5)));
+    let metal = Arc::new(Metal::new(&Colour::new(0.8, 0.6, 0.2), 0.3));
+    world.add(Sphere::new(Point::new(0., -100.5, -1.), 100., ground));
+    world.add(Sphere::new(Point::new(0., 0., -1.), 0.5, diffuse));
+    world.add(Sphere::new(
+        Point::new(-1., 0., -1.),
+        0.5,
+        Arc::new(Dielectric::new(1.5)),
+    ));
+    world.add(Sphere::new(Point::new(1., 0., -1.), 0.5, metal));
+    let world = Bvh::new(world);
+
+    let settings = |sampler, samples_per_pixel, seed| RenderSettings {
+        width: 48,
+        height: 32,
+        samples_per_pixel,
+        max_depth: 8,
+        seed: Some(seed),
+        sampler,
+        ..Default::default()
+    };
+    let camera = Camera::new(
+        &Point::new(-2., 2., 1.),
+        &Point::new(0., 0., -1.),
+        &Vec3::new(0., 1., 0.),
+        30.,
+        1.5,
+        0.2,
+        3.4,
+    );
+    let render =
+        |sampler, spp, seed| Renderer::new(settings(sampler, spp, seed)).render(&world, &camera);
+
+    // A seed of its own, so the reference shares no scrambling with the runs.
+    let start = Instant::now();
+    let reference = render(SamplerKind::Sobol, 8192, 8);
+    println!(
+        "RMS error of a 48x32 render against 8192 Sobol samples per pixel ({:.1?})",
+        start.elapsed()
+    );
+    header();
+    for kind in KINDS {
+        print!("{:<12}", format!("{kind:?}"));
+        let start = Instant::now();
+        for spp in SPP {
+            print!("{:>11.2e}", rms(&render(kind, spp, 7), &reference));
+        }
+        println!("  ({:.1?})", start.elapsed());
+    }
+}
+
+fn header() {
+    print!("{:<12}", "spp");
+    SPP.iter().for_each(|spp| print!("{spp:>11}"));
+    println!();
+}
+
+fn rms(image: &Framebuffer, reference: &Framebuffer) -> f64 {
+    let sum: f64 = image
+        .pixels()
+        .iter()
+        .zip(reference.pixels())
+        .map(|(a, b)| (*a - *b).len_squared())
+        .sum();
+    (sum / (3 * image.pixels().len()) as f64).sqrt()
+}
@@ -0,0 +1,117 @@
+use crate::{ray::Ray, vec3::Point};
+
+/// Axis-aligned bounding box described by its minimum and maximum corners.
+#[derive(Copy, Clone, Debug)]
+pub struct Aabb {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Aabb {
+    pub fn new(a: &Point, b: &Point) -> Self {
+        Self {
+            min: a.min(b),
+            max: a.max(b),
+        }
+    }
+
+    pub fn centroid(&self) -> Point {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn hit(&self, ray: &Ray, mut t_min: f64, mut t_max: f64) -> bool {
+        let orig = ray.origin();
+        let dir = ray.dir();
+
+        for axis in 0..3 {
+            let inv_d = 1. / dir[axis];
+            let mut t0 = (self.min[axis] - orig[axis]) * inv_d;
+            let mut t1 = (self.max[axis] - orig[axis]) * inv_d;
+            if inv_d < 0. {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            // NaNs (ray lying in a slab plane) fall through the comparisons
+            // and leave the interval untouched.
+            if t0 > t_min {
+                t_min = t0;
+            }
+            if t1 < t_max {
+                t_max = t1;
+            }
+            if t_max < t_min {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    pub fn longest_axis(&self) -> usize {
+        let d = self.max - self.min;
+        if d.x > d.y && d.x > d.z {
+            0
+        } else if d.y > d.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let d = self.max - self.min;
+        2. * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    pub fn union(&self, rhs: &Aabb) -> Self {
+        Self {
+            min: self.min.min(&rhs.min),
+            max: self.max.max(&rhs.max),
+        }
+    }
+
+    pub fn union_point(&self, p: &Point) -> Self {
+        Self {
+            min: self.min.min(p),
+            max: self.max.max(p),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn hit() {
+        let bbox = Aabb::new(&Point::new(-1., -1., -1.), &Point::new(1., 1., 1.));
+
+        let ray = Ray::new(&Point::new(0., 0., -5.), &Vec3::new(0., 0., 1.));
+        assert!(bbox.hit(&ray, 0., f64::INFINITY));
+        assert!(!bbox.hit(&ray, 0., 3.));
+
+        let ray = Ray::new(&Point::new(0., 2., -5.), &Vec3::new(0., 0., 1.));
+        assert!(!bbox.hit(&ray, 0., f64::INFINITY));
+
+        let ray = Ray::new(&Point::new(0., 0., 5.), &Vec3::new(0., 0., 1.));
+        assert!(!bbox.hit(&ray, 0., f64::INFINITY));
+    }
+
+    #[test]
+    fn surface_area() {
+        let bbox = Aabb::new(&Point::new(0., 0., 0.), &Point::new(1., 2., 3.));
+        assert!((bbox.surface_area() - 22.).abs() < 1e-10);
+        assert_eq!(bbox.longest_axis(), 2);
+    }
+
+    #[test]
+    fn union() {
+        let a = Aabb::new(&Point::new(0., 0., 0.), &Point::new(1., 1., 1.));
+        let b = Aabb::new(&Point::new(-1., 0.5, 0.), &Point::new(0.5, 2., 0.5));
+        let u = a.union(&b);
+
+        assert_eq!((u.min.x, u.min.y, u.min.z), (-1., 0., 0.));
+        assert_eq!((u.max.x, u.max.y, u.max.z), (1., 2., 1.));
+    }
+}
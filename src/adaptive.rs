@@ -0,0 +1,225 @@
+//! Adaptive sampling. After a first pass over every pixel, further passes only
+//! go to pixels whose estimate is still noisy, until they settle or run out
+//! of samples.
+
+use serde::Deserialize;
+
+use crate::{framebuffer::Framebuffer, vec3::Colour};
+
+/// Fraction of the image's mean luminance below which pixels count as black,
+/// so that noise in the shadows is judged against this rather than against
+/// next to nothing. Relative to the image, so any exposure gives the same.
+const DARK: f64 = 0.01;
+
+/// When a pixel has had enough samples.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AdaptiveSampling {
+    /// Standard error of a pixel's mean luminance, as a fraction of that
+    /// luminance, below which it gets no more samples.
+    pub threshold: f64,
+    /// Most samples a pixel may take. 16 times the first pass when `None`.
+    pub max_samples_per_pixel: Option<i64>,
+    /// Samples each noisy pixel gets per pass. As many as the first pass
+    /// when `None`.
+    pub samples_per_pass: Option<i64>,
+}
+
+impl AdaptiveSampling {
+    pub fn new(threshold: f64) -> Self {
+        Self {
+            threshold,
+            max_samples_per_pixel: None,
+            samples_per_pass: None,
+        }
+    }
+
+    pub fn with_max_samples(mut self, samples: i64) -> Self {
+        self.max_samples_per_pixel = Some(samples);
+        self
+    }
+
+    pub fn with_samples_per_pass(mut self, samples: i64) -> Self {
+        self.samples_per_pass = Some(samples);
+        self
+    }
+
+    /// Most samples per pixel when the first pass takes `first_pass`.
+    pub fn max_samples(&self, first_pass: i64) -> i64 {
+        self.max_samples_per_pixel
+            .unwrap_or(16 * first_pass)
+            .max(first_pass)
+    }
+
+    /// Samples per pass after the first, which takes `first_pass`.
+    pub fn pass_samples(&self, first_pass: i64) -> i64 {
+        self.samples_per_pass.unwrap_or(first_pass).max(1)
+    }
+}
+
+/// Running sums over the samples of one pixel.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PixelStats {
+    sum: Colour,
+    luminance: f64,
+    luminance_sq: f64,
+    hits: u64,
+    count: u64,
+}
+
+impl PixelStats {
+    /// Adds a sample of radiance `c`, from a path that hit something or not.
+    pub fn add(&mut self, c: Colour, hit: bool) {
+        let y = 0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z;
+        self.sum += c;
+        self.luminance += y;
+        self.luminance_sq += y * y;
+        self.hits += hit as u64;
+        self.count += 1;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn mean(&self) -> Colour {
+        self.sum / self.count as f64
+    }
+
+    /// Fraction of samples that hit something.
+    pub fn coverage(&self) -> f64 {
+        self.hits as f64 / self.count as f64
+    }
+
+    /// Estimated standard error of the mean luminance, relative to it or to
+    /// `floor` if that is larger. Unknown, and so infinite, with fewer than
+    /// two samples.
+    pub fn relative_error(&self, floor: f64) -> f64 {
+        if self.count < 2 {
+            return f64::INFINITY;
+        }
+        let n = self.count as f64;
+        let mean = self.luminance / n;
+        let variance = ((self.luminance_sq - mean * self.luminance) / (n - 1.)).max(0.);
+        (variance / n).sqrt() / mean.abs().max(floor)
+    }
+}
+
+/// Which pixels of an image `width` wide get another pass: those with
+/// samples left whose error, or a neighbour's, is above `threshold`. Looking
+/// at neighbours too catches noisy pixels whose few samples happened to agree.
+pub fn noisy_pixels(
+    stats: &[PixelStats],
+    width: usize,
+    threshold: f64,
+    max_samples: u64,
+) -> Vec<bool> {
+    let height = stats.len() / width;
+    let luminance: f64 = stats.iter().map(|s| s.luminance).sum();
+    let count: u64 = stats.iter().map(|s| s.count).sum();
+    let floor = (DARK * luminance / count as f64).max(f64::MIN_POSITIVE);
+    let noisy: Vec<bool> = stats
+        .iter()
+        .map(|s| s.relative_error(floor) > threshold)
+        .collect();
+
+    (0..stats.len())
+        .map(|p| {
+            let (x, y) = (p % width, p / width);
+            let rows = y.saturating_sub(1)..(y + 2).min(height);
+            let cols = x.saturating_sub(1)..(x + 2).min(width);
+            stats[p].count < max_samples
+                && rows
+                    .flat_map(|ny| cols.clone().map(move |nx| ny * width + nx))
+                    .any(|n| noisy[n])
+        })
+        .collect()
+}
+
+/// Grey image of the samples each pixel of `image` took, divided by
+/// `scale`. `None` if it has no sample counts.
+pub fn sample_map(image: &Framebuffer, scale: f64) -> Option<Framebuffer> {
+    let pixels = image
+        .sample_counts()?
+        .iter()
+        .map(|&n| {
+            let v = n as f64 / scale;
+            Colour::new(v, v, v)
+        })
+        .collect();
+    Some(Framebuffer::from_pixels(
+        image.width(),
+        image.height(),
+        pixels,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(samples: &[f64]) -> PixelStats {
+        let mut stats = PixelStats::default();
+        for &y in samples {
+            stats.add(Colour::new(y, y, y), y > 0.);
+        }
+        stats
+    }
+
+    #[test]
+    fn relative_error() {
+        assert_eq!(stats(&[0.5]).relative_error(0.), f64::INFINITY);
+        assert_eq!(stats(&[0.5; 8]).relative_error(0.), 0.);
+
+        // Half 0 and half 1: variance 2/7, mean 0.5, over 8 samples.
+        let s = stats(&[0., 1., 0., 1., 0., 1., 0., 1.]);
+        let expected = (2. / 7. / 8f64).sqrt() / 0.5;
+        assert!((s.relative_error(0.01) - expected).abs() < 1e-12);
+        assert_eq!((s.count(), s.coverage()), (8, 0.5));
+        assert!((s.mean().y - 0.5).abs() < 1e-12);
+
+        // Dark pixels are judged against a floor rather than their mean.
+        let dark = stats(&[0., 0.002, 0., 0.002]);
+        assert!(dark.relative_error(0.01) < 0.1);
+        assert!(dark.relative_error(0.) > 0.1);
+    }
+
+    #[test]
+    fn noisy_pixels_and_neighbours() {
+        let flat = stats(&[0.5; 4]);
+        let noisy = stats(&[0., 1., 0., 1.]);
+
+        // 4x3, one noisy pixel at (0, 1).
+        let mut image = vec![flat; 12];
+        image[4] = noisy;
+        let active = noisy_pixels(&image, 4, 0.1, 16);
+        let expected = [
+            true, true, false, false, //
+            true, true, false, false, //
+            true, true, false, false,
+        ];
+        assert_eq!(active, expected);
+
+        // Pixels that have used up their samples stop.
+        assert!(noisy_pixels(&image, 4, 0.1, 4).iter().all(|a| !a));
+        assert!(noisy_pixels(&image, 4, 10., 16).iter().all(|a| !a));
+
+        // Exposure scales every pixel alike and changes nothing.
+        let mut image = vec![stats(&[5e-6; 4]); 12];
+        image[4] = stats(&[0., 1e-5, 0., 1e-5]);
+        image[11] = stats(&[0., 1e-8, 0., 1e-8]);
+        assert_eq!(noisy_pixels(&image, 4, 0.1, 16), expected);
+    }
+
+    #[test]
+    fn budgets() {
+        let adaptive = AdaptiveSampling::new(0.05);
+        assert_eq!(
+            (adaptive.max_samples(8), adaptive.pass_samples(8)),
+            (128, 8)
+        );
+
+        let adaptive = adaptive.with_max_samples(4).with_samples_per_pass(2);
+        assert_eq!((adaptive.max_samples(8), adaptive.pass_samples(8)), (8, 2));
+    }
+}
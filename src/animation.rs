@@ -0,0 +1,205 @@
+//! Keyframed camera moves and rendering numbered frame sequences.
+
+use std::path::{Path, PathBuf};
+
+use crate::{
+    camera::Camera,
+    exposure::Exposure,
+    motion::Keyframes,
+    vec3::{Point, Vec3},
+};
+
+/// Camera placement keyed over time.
+#[derive(Clone, Debug)]
+pub struct CameraPath {
+    pub lookfrom: Keyframes<Point>,
+    pub lookat: Keyframes<Point>,
+    pub vup: Vec3,
+    /// Vertical field of view in degrees.
+    pub vfov: Keyframes<f64>,
+    pub focus_dist: Keyframes<f64>,
+    /// Sets the aperture from the f-number at every field of view.
+    pub exposure: Option<Exposure>,
+}
+
+impl CameraPath {
+    /// Points `camera` where the path is at `time`, keeping its lens and
+    /// projection. With an exposure, the aperture follows the focal length.
+    pub fn apply(&self, camera: &mut Camera, time: f64) {
+        let vfov = self.vfov.at(time);
+        camera.set_view(
+            &self.lookfrom.at(time),
+            &self.lookat.at(time),
+            &self.vup,
+            vfov,
+            self.focus_dist.at(time),
+        );
+        if let Some(exposure) = &self.exposure {
+            camera.set_aperture(exposure.aperture(vfov));
+        }
+    }
+}
+
+/// A sequence of frames, evenly spaced in scene time.
+#[derive(Clone, Debug)]
+pub struct Animation {
+    pub frames: u32,
+    pub fps: f64,
+    /// Scene time of the first frame.
+    pub start: f64,
+    pub camera: Option<CameraPath>,
+    /// Renders every frame with the same random numbers, so that noise stays
+    /// put instead of crawling from frame to frame.
+    pub fixed_seed: bool,
+}
+
+impl Animation {
+    /// Scene time of `frame`, counting from 0.
+    pub fn frame_time(&self, frame: u32) -> f64 {
+        self.start + frame as f64 / self.fps
+    }
+
+    /// The camera for `frame`, with its shutter interval moved to the frame's
+    /// time.
+    pub fn camera(&self, base: &Camera, frame: u32) -> Camera {
+        let time = self.frame_time(frame);
+        let mut camera = base.clone();
+        if let Some(path) = &self.camera {
+            path.apply(&mut camera, time);
+        }
+
+        let (open, close) = base.shutter();
+        camera.with_shutter(open + time, close + time)
+    }
+
+    /// Seed for `frame`, given the scene's seed. Unseeded scenes stay
+    /// unseeded unless the seed is fixed.
+    pub fn seed(&self, seed: Option<u64>, frame: u32) -> Option<u64> {
+        match (seed, self.fixed_seed) {
+            (Some(seed), true) => Some(seed),
+            (None, true) => Some(0),
+            (seed, false) => seed.map(|s| s.wrapping_add(frame as u64)),
+        }
+    }
+}
+
+/// Output path of `frame` (counting from 0): `frame.png` becomes
+/// `frame_0001.png`, `frame_0002.png`, ...
+pub fn frame_path(output: &Path, frame: u32) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match output.extension() {
+        Some(ext) => format!("{stem}_{:04}.{}", frame + 1, ext.to_string_lossy()),
+        None => format!("{stem}_{:04}", frame + 1),
+    };
+    output.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::{Sampler, SamplerKind};
+
+    fn animation(camera: Option<CameraPath>) -> Animation {
+        Animation {
+            frames: 25,
+            fps: 24.,
+            start: 1.,
+            camera,
+            fixed_seed: false,
+        }
+    }
+
+    #[test]
+    fn frame_paths() {
+        assert_eq!(
+            frame_path(Path::new("out/frame.png"), 0),
+            Path::new("out/frame_0001.png")
+        );
+        assert_eq!(frame_path(Path::new("shot"), 11), Path::new("shot_0012"));
+    }
+
+    #[test]
+    fn camera_follows_path() {
+        let sampler = &mut Sampler::new(SamplerKind::Random, 1, 7);
+        let base = Camera::new(
+            &Point::new(0., 0., 0.),
+            &Point::new(0., 0., -1.),
+            &Vec3::new(0., 1., 0.),
+            90.,
+            2.,
+            0.,
+            1.,
+        )
+        .with_shutter(0., 0.5 / 24.);
+        let path = CameraPath {
+            lookfrom: Keyframes::new(vec![
+                (1., Point::new(0., 0., 0.)),
+                (2., Point::new(4., 0., 0.)),
+            ]),
+            lookat: Keyframes::constant(Point::new(2., 0., -2.)),
+            vup: Vec3::new(0., 1., 0.),
+            vfov: Keyframes::constant(90.),
+            focus_dist: Keyframes::new(vec![(1., 1.), (2., 3.)]),
+            exposure: None,
+        };
+        let anim = animation(Some(path));
+
+        // Halfway through, the camera is at x = 2 looking straight at -z.
+        let cam = anim.camera(&base, 12);
+        assert!((anim.frame_time(12) - 1.5).abs() < 1e-12);
+        assert_eq!(cam.shutter(), (1.5, 1.5 + 0.5 / 24.));
+        assert!((cam.focus_dist() - 2.).abs() < 1e-12);
+
+        let ray = cam.get_ray(0.5, 0.5, sampler).unwrap();
+        let (o, d) = (ray.origin(), ray.dir().unit_vector());
+        assert!((o - Point::new(2., 0., 0.)).len() < 1e-12, "{o}");
+        assert!((d - Vec3::new(0., 0., -1.)).len() < 1e-12, "{d}");
+
+        // The aspect ratio survives: the top-right corner is at 2:1 in x.
+        let corner = cam.get_ray(1., 1., sampler).unwrap().dir();
+        assert!((corner.x / corner.y - 2.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn aperture_follows_zoom() {
+        let base = Camera::new(
+            &Point::new(0., 0., 0.),
+            &Point::new(0., 0., -1.),
+            &Vec3::new(0., 1., 0.),
+            90.,
+            2.,
+            0.,
+            1.,
+        );
+        let exposure = Exposure {
+            f_number: 2.8,
+            ..Default::default()
+        };
+        let path = CameraPath {
+            lookfrom: Keyframes::constant(Point::new(0., 0., 0.)),
+            lookat: Keyframes::constant(Point::new(0., 0., -1.)),
+            vup: Vec3::new(0., 1., 0.),
+            vfov: Keyframes::new(vec![(1., 90.), (2., 20.)]),
+            focus_dist: Keyframes::constant(1.),
+            exposure: Some(exposure),
+        };
+        let anim = animation(Some(path));
+
+        for (frame, vfov) in [(0, 90.), (24, 20.)] {
+            let aperture = anim.camera(&base, frame).aperture();
+            assert!((aperture - exposure.aperture(vfov)).abs() < 1e-12);
+        }
+        assert!(anim.camera(&base, 24).aperture() > 4. * anim.camera(&base, 0).aperture());
+    }
+
+    #[test]
+    fn seeds() {
+        let mut anim = animation(None);
+        assert_eq!(anim.seed(None, 3), None);
+        assert_eq!(anim.seed(Some(10), 3), Some(13));
+
+        anim.fixed_seed = true;
+        assert_eq!(anim.seed(Some(10), 3), Some(10));
+        assert_eq!(anim.seed(None, 3), anim.seed(None, 4));
+    }
+}
@@ -0,0 +1,206 @@
+//! Lens aperture shapes, which give defocused highlights (bokeh) their outline.
+
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{
+    sampler::{self, Sampler},
+    texture::ImageTexture,
+    vec3::Vec3,
+};
+
+#[derive(Clone, Default)]
+pub enum ApertureShape {
+    #[default]
+    Circle,
+    /// Regular polygon formed by `blades` diaphragm blades. `rotation` is the
+    /// angle of the first corner in degrees from the lens's horizontal axis,
+    /// `rounding` blends from straight edges (0) to a circle (1).
+    Polygon {
+        blades: u32,
+        rotation: f64,
+        rounding: f64,
+    },
+    Mask(Arc<ApertureMask>),
+}
+
+impl ApertureShape {
+    /// Uniform random point on the aperture, within `[-1, 1]` on both axes.
+    pub fn sample(&self, sampler: &mut Sampler) -> Vec3 {
+        match self {
+            ApertureShape::Circle => sampler::disk(sampler.get_2d()),
+            ApertureShape::Polygon { .. } => {
+                // Points outside the blades are tried again with plain
+                // random numbers.
+                let (u, v) = sampler.get_2d();
+                let mut p = Vec3::new(2. * u - 1., 2. * v - 1., 0.);
+                let rng = sampler.rng();
+                while !self.contains(p.x, p.y) {
+                    p = Vec3::new(rng.random_range(-1., 1.), rng.random_range(-1., 1.), 0.);
+                }
+                p
+            }
+            ApertureShape::Mask(mask) => mask.sample(sampler.get_2d()),
+        }
+    }
+
+    /// Whether the point `(x, y)` lets light through. Masks count any
+    /// non-black pixel as open.
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        let r = x.hypot(y);
+        match self {
+            ApertureShape::Circle => r <= 1.,
+            ApertureShape::Polygon {
+                blades,
+                rotation,
+                rounding,
+            } => {
+                let sector = 2. * PI / *blades as f64;
+                let theta = (y.atan2(x) - rotation.to_radians()).rem_euclid(sector);
+                let edge = (sector / 2.).cos() / (theta - sector / 2.).cos();
+                r <= edge + rounding * (1. - edge)
+            }
+            ApertureShape::Mask(mask) => mask.weight(x, y) > 0.,
+        }
+    }
+}
+
+/// Aperture drawn as an image: brighter pixels let more light through. The
+/// image covers the square `[-1, 1]` on both lens axes.
+pub struct ApertureMask {
+    width: usize,
+    height: usize,
+    /// Running sum of the pixel weights, normalised to end at 1.
+    cdf: Vec<f64>,
+}
+
+impl ApertureMask {
+    /// Builds a mask from an image's luminance. Returns `None` when the image
+    /// is completely black.
+    pub fn new(image: &ImageTexture) -> Option<Self> {
+        let (width, height) = (image.width(), image.height());
+        let mut total = 0.;
+        let mut cdf = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let c = image.pixel(x, y);
+                total += (0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z).max(0.);
+                cdf.push(total);
+            }
+        }
+
+        if total <= 0. {
+            return None;
+        }
+        cdf.iter_mut().for_each(|c| *c /= total);
+
+        Some(Self { width, height, cdf })
+    }
+
+    /// Picks a pixel with `u` and reuses where `u` falls within the pixel's
+    /// share of the weight for the horizontal position inside it.
+    fn sample(&self, (u, v): (f64, f64)) -> Vec3 {
+        let i = self
+            .cdf
+            .partition_point(|&c| c <= u)
+            .min(self.cdf.len() - 1);
+        let start = if i == 0 { 0. } else { self.cdf[i - 1] };
+        let within = ((u - start) / (self.cdf[i] - start)).clamp(0., 1.);
+        let x = (i % self.width) as f64 + within;
+        let y = (i / self.width) as f64 + v;
+
+        // Image rows run top to bottom.
+        Vec3::new(
+            2. * x / self.width as f64 - 1.,
+            1. - 2. * y / self.height as f64,
+            0.,
+        )
+    }
+
+    fn weight(&self, x: f64, y: f64) -> f64 {
+        if x.abs() > 1. || y.abs() > 1. {
+            return 0.;
+        }
+        let px = (((x + 1.) / 2. * self.width as f64) as usize).min(self.width - 1);
+        let py = (((1. - y) / 2. * self.height as f64) as usize).min(self.height - 1);
+        let i = py * self.width + px;
+
+        self.cdf[i] - if i == 0 { 0. } else { self.cdf[i - 1] }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sampler::SamplerKind, vec3::Colour};
+
+    #[test]
+    fn polygon_corners() {
+        let diamond = ApertureShape::Polygon {
+            blades: 4,
+            rotation: 0.,
+            rounding: 0.,
+        };
+        assert!(diamond.contains(0.99, 0.));
+        assert!(!diamond.contains(0.6, 0.6));
+
+        let square = ApertureShape::Polygon {
+            blades: 4,
+            rotation: 45.,
+            rounding: 0.,
+        };
+        assert!(square.contains(0.7, 0.7));
+        assert!(!square.contains(0.99, 0.));
+    }
+
+    #[test]
+    fn rounding_blends_to_circle() {
+        let shape = |rounding| ApertureShape::Polygon {
+            blades: 5,
+            rotation: 0.,
+            rounding,
+        };
+        let (x, y) = (0.95 * (0.2 * PI).cos(), 0.95 * (0.2 * PI).sin());
+        assert!(!shape(0.).contains(x, y));
+        assert!(shape(1.).contains(x, y));
+        assert!(!shape(1.).contains(1.01, 0.));
+    }
+
+    #[test]
+    fn samples_stay_inside() {
+        let sampler = &mut Sampler::new(SamplerKind::Random, 1, 7);
+        let hexagon = ApertureShape::Polygon {
+            blades: 6,
+            rotation: 15.,
+            rounding: 0.2,
+        };
+        for shape in [ApertureShape::Circle, hexagon] {
+            for _ in 0..1000 {
+                let p = shape.sample(sampler);
+                assert!(shape.contains(p.x, p.y), "{p}");
+            }
+        }
+    }
+
+    #[test]
+    fn mask() {
+        let sampler = &mut Sampler::new(SamplerKind::Random, 1, 7);
+        // Only the left column lets light through.
+        let white = Colour::new(1., 1., 1.);
+        let black = Colour::new(0., 0., 0.);
+        let image = ImageTexture::new(2, 2, vec![white, black, white, black]);
+        let shape = ApertureShape::Mask(Arc::new(ApertureMask::new(&image).unwrap()));
+
+        for _ in 0..1000 {
+            let p = shape.sample(sampler);
+            assert!(
+                (-1. ..=0.).contains(&p.x) && (-1. ..=1.).contains(&p.y),
+                "{p}"
+            );
+        }
+        assert!(shape.contains(-0.5, 0.5));
+        assert!(!shape.contains(0.5, 0.5));
+
+        let dark = ImageTexture::new(1, 1, vec![black]);
+        assert!(ApertureMask::new(&dark).is_none());
+    }
+}
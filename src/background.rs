@@ -0,0 +1,56 @@
+use crate::{
+    colour::ColourSpace,
+    constants::{BLACK, BLUE, WHITE},
+    ray::Ray,
+    vec3::Colour,
+};
+
+/// Radiance seen by rays that escape the scene.
+#[derive(Copy, Clone, Debug)]
+pub enum Background {
+    /// No environment light: only emissive materials light the scene.
+    None,
+    Solid(Colour),
+    /// Vertical blend from `bottom` (looking straight down) to `top`.
+    Gradient {
+        bottom: Colour,
+        top: Colour,
+    },
+}
+
+impl Background {
+    /// Converts the sRGB colours of the background to the working space
+    /// `space`.
+    pub fn in_colour_space(self, space: ColourSpace) -> Self {
+        match self {
+            Background::None => Background::None,
+            Background::Solid(c) => Background::Solid(space.from_srgb(&c)),
+            Background::Gradient { bottom, top } => Background::Gradient {
+                bottom: space.from_srgb(&bottom),
+                top: space.from_srgb(&top),
+            },
+        }
+    }
+
+    pub fn colour(&self, ray: &Ray) -> Colour {
+        match self {
+            Background::None => BLACK,
+            Background::Solid(c) => *c,
+            Background::Gradient { bottom, top } => {
+                let unit_dir = ray.dir().unit_vector();
+                let t = 0.5 * (unit_dir.y + 1.);
+                (1. - t) * *bottom + t * *top
+            }
+        }
+    }
+}
+
+impl Default for Background {
+    /// The white to sky blue gradient of the book.
+    fn default() -> Self {
+        Background::Gradient {
+            bottom: WHITE,
+            top: BLUE,
+        }
+    }
+}
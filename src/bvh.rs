@@ -0,0 +1,473 @@
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable, HittableList},
+    ray::Ray,
+    vec3::Point,
+};
+
+const SAH_BUCKETS: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+// Cost of visiting an interior node relative to testing one primitive.
+const TRAVERSAL_COST: f64 = 0.125;
+// Deepest the tree may grow, which bounds the traversal stack. Past half of
+// it nodes split at the median, which finishes any tree in time.
+const MAX_DEPTH: usize = 64;
+
+struct Primitive {
+    bbox: Aabb,
+    centroid: Point,
+    obj: Box<dyn Hittable>,
+}
+
+enum Node {
+    Leaf {
+        bbox: Aabb,
+        first: usize,
+        count: usize,
+    },
+    // The first child always immediately follows its parent in `nodes`.
+    Interior {
+        bbox: Aabb,
+        second_child: usize,
+        axis: usize,
+    },
+}
+
+impl Node {
+    fn bbox(&self) -> &Aabb {
+        match self {
+            Node::Leaf { bbox, .. } | Node::Interior { bbox, .. } => bbox,
+        }
+    }
+}
+
+/// Bounding volume hierarchy built with the surface area heuristic.
+///
+/// Objects without a bounding box are kept aside and tested linearly on every ray.
+pub struct Bvh {
+    nodes: Vec<Node>,
+    objects: Vec<Box<dyn Hittable>>,
+    unbounded: Vec<Box<dyn Hittable>>,
+}
+
+impl Bvh {
+    pub fn new(list: HittableList) -> Self {
+        let mut prims = Vec::with_capacity(list.objects.len());
+        let mut unbounded = Vec::new();
+
+        for obj in list.objects {
+            match obj.bounding_box() {
+                Some(bbox) => prims.push(Primitive {
+                    bbox,
+                    centroid: bbox.centroid(),
+                    obj,
+                }),
+                None => unbounded.push(obj),
+            }
+        }
+
+        let mut nodes = Vec::new();
+        if !prims.is_empty() {
+            build(&mut prims, 0, 0, &mut nodes);
+        }
+
+        Self {
+            nodes,
+            objects: prims.into_iter().map(|p| p.obj).collect(),
+            unbounded,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len() + self.unbounded.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn build(prims: &mut [Primitive], offset: usize, depth: usize, nodes: &mut Vec<Node>) -> usize {
+    let idx = nodes.len();
+    let n = prims.len();
+
+    let bbox = prims[1..]
+        .iter()
+        .fold(prims[0].bbox, |b, p| b.union(&p.bbox));
+
+    let split = match n {
+        1 => None,
+        _ if depth < MAX_DEPTH / 2 => split(prims, &bbox),
+        _ if n <= MAX_LEAF_SIZE => None,
+        _ => {
+            let extent = bbox.max - bbox.min;
+            let axis = (0..3)
+                .max_by(|&a, &b| extent[a].total_cmp(&extent[b]))
+                .unwrap_or(0);
+            Some((median_split(prims, axis), axis))
+        }
+    };
+
+    let (mid, axis) = match split {
+        Some(split) => split,
+        None => {
+            nodes.push(Node::Leaf {
+                bbox,
+                first: offset,
+                count: n,
+            });
+            return idx;
+        }
+    };
+
+    nodes.push(Node::Interior {
+        bbox,
+        second_child: 0,
+        axis,
+    });
+
+    build(&mut prims[..mid], offset, depth + 1, nodes);
+    let second = build(&mut prims[mid..], offset + mid, depth + 1, nodes);
+
+    if let Node::Interior { second_child, .. } = &mut nodes[idx] {
+        *second_child = second;
+    }
+
+    idx
+}
+
+/// Partitions `prims` in place and returns the split index and axis, or
+/// `None` when a leaf is cheaper than any split.
+fn split(prims: &mut [Primitive], bbox: &Aabb) -> Option<(usize, usize)> {
+    let n = prims.len();
+    let centroids = Aabb::new(&prims[0].centroid, &prims[0].centroid);
+    let centroids = prims
+        .iter()
+        .fold(centroids, |b, p| b.union_point(&p.centroid));
+
+    let bucket_of = |p: &Primitive, axis: usize| {
+        let lo = centroids.min[axis];
+        let extent = centroids.max[axis] - lo;
+        let b = (SAH_BUCKETS as f64 * (p.centroid[axis] - lo) / extent) as usize;
+        b.min(SAH_BUCKETS - 1)
+    };
+
+    let mut best: Option<(f64, usize, usize)> = None;
+
+    for axis in 0..3 {
+        if centroids.max[axis] - centroids.min[axis] <= 0. {
+            continue;
+        }
+
+        let mut counts = [0usize; SAH_BUCKETS];
+        let mut bounds: [Option<Aabb>; SAH_BUCKETS] = [None; SAH_BUCKETS];
+        for p in prims.iter() {
+            let b = bucket_of(p, axis);
+            counts[b] += 1;
+            bounds[b] = Some(bounds[b].map_or(p.bbox, |bb| bb.union(&p.bbox)));
+        }
+
+        // Sweep from the right so each split's right-hand cost is available
+        // when sweeping from the left.
+        let mut right_cost = [0.; SAH_BUCKETS];
+        let mut acc: Option<Aabb> = None;
+        let mut count = 0;
+        for b in (1..SAH_BUCKETS).rev() {
+            acc = union_opt(acc, bounds[b]);
+            count += counts[b];
+            right_cost[b - 1] = count as f64 * acc.map_or(0., |a| a.surface_area());
+        }
+
+        let mut acc: Option<Aabb> = None;
+        let mut count = 0;
+        for b in 0..SAH_BUCKETS - 1 {
+            acc = union_opt(acc, bounds[b]);
+            count += counts[b];
+            let left_cost = count as f64 * acc.map_or(0., |a| a.surface_area());
+            let cost = TRAVERSAL_COST + (left_cost + right_cost[b]) / bbox.surface_area();
+
+            if best.is_none_or(|(c, _, _)| cost < c) {
+                best = Some((cost, axis, b));
+            }
+        }
+    }
+
+    let leaf_cost = n as f64;
+
+    match best {
+        Some((cost, _, _)) if n <= MAX_LEAF_SIZE && cost >= leaf_cost => None,
+        Some((_, axis, bucket)) => {
+            let mid = partition(prims, |p| bucket_of(p, axis) <= bucket);
+            if mid == 0 || mid == n {
+                Some((median_split(prims, axis), axis))
+            } else {
+                Some((mid, axis))
+            }
+        }
+        // Every centroid coincides: no plane separates them.
+        None if n <= MAX_LEAF_SIZE => None,
+        None => Some((median_split(prims, 0), 0)),
+    }
+}
+
+fn union_opt(a: Option<Aabb>, b: Option<Aabb>) -> Option<Aabb> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.union(&b)),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+fn median_split(prims: &mut [Primitive], axis: usize) -> usize {
+    let mid = prims.len() / 2;
+    prims.select_nth_unstable_by(mid, |a, b| a.centroid[axis].total_cmp(&b.centroid[axis]));
+    mid
+}
+
+fn partition(prims: &mut [Primitive], pred: impl Fn(&Primitive) -> bool) -> usize {
+    let mut mid = 0;
+    for i in 0..prims.len() {
+        if pred(&prims[i]) {
+            prims.swap(i, mid);
+            mid += 1;
+        }
+    }
+    mid
+}
+
+impl Hittable for Bvh {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut is_hit: Option<HitRecord> = None;
+        let mut closest = t_max;
+
+        for obj in &self.unbounded {
+            if let Some(rec) = obj.hit(ray, t_min, closest) {
+                closest = rec.t;
+                is_hit = Some(rec);
+            }
+        }
+
+        if self.nodes.is_empty() {
+            return is_hit;
+        }
+
+        let dir = ray.dir();
+        let dir_is_neg = [dir.x < 0., dir.y < 0., dir.z < 0.];
+        let mut stack = [0; MAX_DEPTH];
+        let mut stack_len = 0;
+        let mut idx = 0;
+
+        loop {
+            let node = &self.nodes[idx];
+            if node.bbox().hit(ray, t_min, closest) {
+                match node {
+                    Node::Leaf { first, count, .. } => {
+                        for obj in &self.objects[*first..*first + *count] {
+                            if let Some(rec) = obj.hit(ray, t_min, closest) {
+                                closest = rec.t;
+                                is_hit = Some(rec);
+                            }
+                        }
+                    }
+                    // Visit the child nearer to the ray origin first so that
+                    // `closest` shrinks as early as possible.
+                    Node::Interior {
+                        second_child, axis, ..
+                    } => {
+                        if dir_is_neg[*axis] {
+                            stack[stack_len] = idx + 1;
+                            idx = *second_child;
+                        } else {
+                            stack[stack_len] = *second_child;
+                            idx += 1;
+                        }
+                        stack_len += 1;
+                        continue;
+                    }
+                }
+            }
+
+            if stack_len == 0 {
+                break;
+            }
+            stack_len -= 1;
+            idx = stack[stack_len];
+        }
+
+        is_hit
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        if !self.unbounded.is_empty() {
+            return None;
+        }
+
+        self.nodes.first().map(|n| *n.bbox())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        material::{Lambertian, Material},
+        rng::Rng,
+        sphere::{MovingSphere, Sphere},
+        vec3::{Colour, Vec3},
+    };
+
+    fn random_point(rng: &mut Rng, extent: f64) -> Point {
+        Point::new(
+            rng.random_range(-extent, extent),
+            rng.random_range(-extent, extent),
+            rng.random_range(-extent, extent),
+        )
+    }
+
+    fn random_spheres(rng: &mut Rng, n: usize) -> (HittableList, HittableList) {
+        let mut list = HittableList::new();
+        let mut bvh_list = HittableList::new();
+
+        for _ in 0..n {
+            let centre = random_point(rng, 20.);
+            let r = rng.random_range(0.1, 2.);
+            let mat: Arc<dyn Material> = Arc::new(Lambertian::new(&Colour::default()));
+            list.add(Sphere::new(centre, r, mat.clone()));
+            bvh_list.add(Sphere::new(centre, r, mat));
+        }
+
+        (list, bvh_list)
+    }
+
+    fn assert_same_hit(lhs: &Option<HitRecord>, rhs: &Option<HitRecord>) {
+        match (lhs, rhs) {
+            (None, None) => {}
+            (Some(l), Some(r)) => {
+                assert_eq!(l.t, r.t);
+                assert_eq!((l.p.x, l.p.y, l.p.z), (r.p.x, r.p.y, r.p.z));
+                assert_eq!((l.n.x, l.n.y, l.n.z), (r.n.x, r.n.y, r.n.z));
+                assert_eq!(l.front_face, r.front_face);
+                assert!(Arc::ptr_eq(&l.mat, &r.mat));
+            }
+            _ => panic!("BVH and list disagree on whether the ray hits"),
+        }
+    }
+
+    #[test]
+    fn matches_linear_list() {
+        let mut rng = Rng::new(0x5eed, 0);
+        let (list, bvh_list) = random_spheres(&mut rng, 500);
+        let bvh = Bvh::new(bvh_list);
+        assert_eq!(bvh.len(), 500);
+
+        let mut hits = 0;
+        for _ in 0..5000 {
+            let orig = random_point(&mut rng, 30.);
+            let target = random_point(&mut rng, 20.);
+            let ray = Ray::new(&orig, &(target - orig));
+
+            let expected = list.hit(&ray, 0.001, f64::INFINITY);
+            hits += expected.is_some() as usize;
+            assert_same_hit(&expected, &bvh.hit(&ray, 0.001, f64::INFINITY));
+        }
+
+        // Make sure the comparison is not vacuous.
+        assert!(hits > 1000);
+    }
+
+    #[test]
+    fn matches_linear_list_with_bounded_interval() {
+        let mut rng = Rng::new(42, 0);
+        let (list, bvh_list) = random_spheres(&mut rng, 100);
+        let bvh = Bvh::new(bvh_list);
+
+        for _ in 0..2000 {
+            let orig = random_point(&mut rng, 30.);
+            let dir = Vec3::random_unit_vec(&mut rng);
+            let ray = Ray::new(&orig, &dir);
+            let t_max = rng.random_range(1., 40.);
+
+            assert_same_hit(&list.hit(&ray, 0.001, t_max), &bvh.hit(&ray, 0.001, t_max));
+        }
+    }
+
+    #[test]
+    fn moving_objects() {
+        let mut rng = Rng::new(7, 0);
+        let mut list = HittableList::new();
+        let mut bvh_list = HittableList::new();
+        for _ in 0..200 {
+            let (from, to) = (random_point(&mut rng, 20.), random_point(&mut rng, 20.));
+            let r = rng.random_range(0.2, 1.5);
+            let mat: Arc<dyn Material> = Arc::new(Lambertian::new(&Colour::default()));
+            list.add(MovingSphere::new(from, 0., to, 1., r, mat.clone()));
+            bvh_list.add(MovingSphere::new(from, 0., to, 1., r, mat));
+        }
+        let bvh = Bvh::new(bvh_list);
+
+        let mut hits = 0;
+        for _ in 0..2000 {
+            let orig = random_point(&mut rng, 30.);
+            let target = random_point(&mut rng, 20.);
+            let ray = Ray::new(&orig, &(target - orig)).with_time(rng.random());
+
+            let expected = list.hit(&ray, 0.001, f64::INFINITY);
+            hits += expected.is_some() as usize;
+            assert_same_hit(&expected, &bvh.hit(&ray, 0.001, f64::INFINITY));
+        }
+        assert!(hits > 200);
+    }
+
+    #[test]
+    fn deep_trees_fit_the_stack() {
+        // Exponentially spaced spheres each split off on their own, which
+        // would nest far deeper than the traversal stack.
+        let (mut list, mut bvh_list) = (HittableList::new(), HittableList::new());
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(&Colour::default()));
+        for i in 0..200 {
+            let centre = Point::new(16f64.powi(i), 0., 0.);
+            list.add(Sphere::new(centre, 0.1, mat.clone()));
+            bvh_list.add(Sphere::new(centre, 0.1, mat.clone()));
+        }
+        let bvh = Bvh::new(bvh_list);
+
+        for i in 0..200 {
+            let x = 16f64.powi(i) + 0.05;
+            let ray = Ray::new(&Point::new(x, 0., 5.), &Vec3::new(0., 0., -1.));
+            let expected = list.hit(&ray, 0.001, f64::INFINITY);
+            assert!(expected.is_some());
+            assert_same_hit(&expected, &bvh.hit(&ray, 0.001, f64::INFINITY));
+        }
+    }
+
+    #[test]
+    fn coincident_centroids() {
+        let mut list = HittableList::new();
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(&Colour::default()));
+        for i in 1..=20 {
+            list.add(Sphere::new(Point::default(), i as f64, mat.clone()));
+        }
+        let bvh = Bvh::new(list);
+
+        let ray = Ray::new(&Point::new(0., 0., -50.), &Vec3::new(0., 0., 1.));
+        let rec = bvh.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 30.).abs() < 1e-10);
+    }
+
+    #[test]
+    fn bounding_box() {
+        let mut list = HittableList::new();
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(&Colour::default()));
+        list.add(Sphere::new(Point::new(-1., 0., 0.), 1., mat.clone()));
+        list.add(Sphere::new(Point::new(2., 0., 0.), 0.5, mat));
+
+        let expected = list.bounding_box().unwrap();
+        let bbox = Bvh::new(list).bounding_box().unwrap();
+
+        assert_eq!((bbox.min.x, bbox.min.y, bbox.min.z), (-2., -1., -1.));
+        assert_eq!((bbox.max.x, bbox.max.y, bbox.max.z), (2.5, 1., 1.));
+        assert_eq!(expected.surface_area(), bbox.surface_area());
+    }
+}
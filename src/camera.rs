@@ -1,8 +1,114 @@
+use std::f64::consts::PI;
+
+use serde::Deserialize;
+
 use crate::{
+    aperture::ApertureShape,
+    hittable::Hittable,
     ray::Ray,
+    sampler::Sampler,
     vec3::{Point, Vec3},
 };
 
+/// How image coordinates map to ray directions. Only the perspective
+/// projection models the lens; the others are pinholes.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Projection {
+    #[default]
+    Perspective,
+    /// Parallel rays along the view direction through a window `height`
+    /// world units tall.
+    Orthographic { height: f64 },
+    /// Circular fisheye covering `fov` degrees across the image height.
+    /// Pixels outside the circle see nothing.
+    Fisheye {
+        fov: f64,
+        #[serde(default)]
+        mapping: FisheyeMapping,
+    },
+    /// Full 360 by 180 degree panorama, best rendered at a 2:1 aspect ratio.
+    /// The view direction is at the centre of the image.
+    Equirectangular,
+    /// One 90 degree face of a cube map around the camera.
+    CubeFace { face: CubeFace },
+}
+
+/// Radial distance on the image as a function of the angle `theta` off axis.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FisheyeMapping {
+    /// `r ∝ theta`
+    #[default]
+    Equidistant,
+    /// `r ∝ sin(theta / 2)`, preserving solid angle.
+    Equisolid,
+}
+
+/// Cube faces, named relative to the camera's view.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CubeFace {
+    Front,
+    Back,
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StereoLayout {
+    /// Left eye in the left half of the image.
+    SideBySide,
+    /// Left eye in the top half of the image.
+    OverUnder,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Eye {
+    Left,
+    Right,
+}
+
+/// Stereo pair rendering. Eyes sit `interocular` world units apart and see
+/// objects at distance `convergence` at the same place in both images;
+/// infinite convergence keeps the eyes parallel.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Stereo {
+    pub layout: StereoLayout,
+    pub interocular: f64,
+    #[serde(default = "parallel")]
+    pub convergence: f64,
+}
+
+fn parallel() -> f64 {
+    f64::INFINITY
+}
+
+impl Stereo {
+    /// Sizes of the left and right eye images in a `width` by `height` frame.
+    /// The left eye gets the extra pixel of an odd size.
+    pub fn eye_sizes(&self, width: i32, height: i32) -> [(i32, i32); 2] {
+        match self.layout {
+            StereoLayout::SideBySide => [(width - width / 2, height), (width / 2, height)],
+            StereoLayout::OverUnder => [(width, height - height / 2), (width, height / 2)],
+        }
+    }
+
+    /// Whether a `width` by `height` frame leaves each eye at least a pixel.
+    pub fn fits(&self, width: i32, height: i32) -> bool {
+        self.eye_sizes(width, height)
+            .iter()
+            .all(|&(w, h)| w > 0 && h > 0)
+    }
+}
+
+/// Thin-lens camera. Points at `focus_dist` along the view direction are
+/// sharp; elsewhere the blur grows with the aperture.
+#[derive(Clone)]
 pub struct Camera {
     origin: Point,
     low_left_corner: Point,
@@ -12,6 +118,16 @@ pub struct Camera {
     v: Vec3,
     w: Vec3,
     lens_radius: f64,
+    focus_dist: f64,
+    aperture: ApertureShape,
+    squeeze: f64,
+    vignetting: f64,
+    projection: Projection,
+    /// Omni-directional stereo: signed distance of the eye from the centre of
+    /// projection, and the distance at which the eyes converge.
+    ods: Option<(f64, f64)>,
+    /// Times at which the shutter opens and closes.
+    shutter: (f64, f64),
 }
 
 impl Camera {
@@ -24,35 +140,524 @@ impl Camera {
         aperture: f64,
         focus_dist: f64,
     ) -> Self {
-        let theta = vfov.to_radians();
-        let h = (theta / 2.).tan();
-        let viewport_h = 2. * h;
+        let mut cam = Self {
+            origin: *from,
+            low_left_corner: Point::default(),
+            hor: Vec3::new(aspect_ratio, 0., 0.),
+            ver: Vec3::new(0., 1., 0.),
+            u: Vec3::default(),
+            v: Vec3::default(),
+            w: Vec3::default(),
+            lens_radius: aperture / 2.,
+            focus_dist,
+            aperture: ApertureShape::Circle,
+            squeeze: 1.,
+            vignetting: 0.,
+            projection: Projection::Perspective,
+            ods: None,
+            shutter: (0., 0.),
+        };
+        cam.set_view(from, to, vup, vfov, focus_dist);
+        cam
+    }
+
+    /// Points the camera from `from` to `to`, keeping its aspect ratio, lens
+    /// and projection.
+    pub fn set_view(&mut self, from: &Point, to: &Point, vup: &Vec3, vfov: f64, focus_dist: f64) {
+        let aspect_ratio = self.hor.len() / self.ver.len();
+        let viewport_h = 2. * (vfov.to_radians() / 2.).tan();
         let viewport_w = aspect_ratio * viewport_h;
 
-        let w = (*from - *to).unit_vector();
-        let u = vup.cross(&w).unit_vector();
-        let v = w.cross(&u);
-
-        let hor = focus_dist * viewport_w * u;
-        let ver = focus_dist * viewport_h * v;
-        let origin = *from;
-
-        Self {
-            origin,
-            hor,
-            ver,
-            low_left_corner: origin - hor / 2. - ver / 2. - focus_dist * w,
-            u,
-            v,
-            w,
-            lens_radius: aperture / 2.,
+        self.w = (*from - *to).unit_vector();
+        self.u = vup.cross(&self.w).unit_vector();
+        self.v = self.w.cross(&self.u);
+
+        self.origin = *from;
+        self.hor = focus_dist * viewport_w * self.u;
+        self.ver = focus_dist * viewport_h * self.v;
+        self.low_left_corner = self.origin - self.hor / 2. - self.ver / 2. - focus_dist * self.w;
+        self.focus_dist = focus_dist;
+    }
+
+    /// Keeps the shutter open from `open` to `close`, blurring anything that
+    /// moves in between.
+    pub fn with_shutter(mut self, open: f64, close: f64) -> Self {
+        self.shutter = (open, close);
+        self
+    }
+
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    pub fn with_aperture_shape(mut self, shape: ApertureShape) -> Self {
+        self.aperture = shape;
+        self
+    }
+
+    /// Anamorphic lens: the aperture is narrowed horizontally by `squeeze`,
+    /// giving tall oval bokeh.
+    pub fn with_anamorphic_squeeze(mut self, squeeze: f64) -> Self {
+        self.squeeze = squeeze;
+        self
+    }
+
+    /// Optical vignetting: light also has to pass a second, unit-size stop
+    /// that shifts by `vignetting` aperture radii between the image centre
+    /// and its edges, clipping off-centre bokeh into cat's eyes and darkening
+    /// the corners.
+    pub fn with_vignetting(mut self, vignetting: f64) -> Self {
+        self.vignetting = vignetting;
+        self
+    }
+
+    pub fn focus_dist(&self) -> f64 {
+        self.focus_dist
+    }
+
+    pub fn shutter(&self) -> (f64, f64) {
+        self.shutter
+    }
+
+    /// Widens or narrows the viewport to a new aspect ratio, keeping the
+    /// vertical field of view.
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: f64) {
+        let centre = self.low_left_corner + self.hor / 2. + self.ver / 2.;
+        self.hor *= aspect_ratio * self.ver.len() / self.hor.len();
+        self.low_left_corner = centre - self.hor / 2. - self.ver / 2.;
+    }
+
+    /// Moves the plane of focus, keeping the field of view.
+    pub fn set_focus_dist(&mut self, focus_dist: f64) {
+        let scale = focus_dist / self.focus_dist;
+        self.hor *= scale;
+        self.ver *= scale;
+        self.low_left_corner = self.origin - self.hor / 2. - self.ver / 2. - focus_dist * self.w;
+        self.focus_dist = focus_dist;
+    }
+
+    /// Diameter of the lens.
+    pub fn aperture(&self) -> f64 {
+        2. * self.lens_radius
+    }
+
+    pub fn set_aperture(&mut self, aperture: f64) {
+        self.lens_radius = aperture / 2.;
+    }
+
+    /// Focuses on whatever the pinhole ray through `(u, v)` hits first.
+    /// Returns the new focus distance, or `None` (leaving the focus
+    /// unchanged) when the ray escapes the scene.
+    pub fn autofocus(&mut self, world: &dyn Hittable, u: f64, v: f64) -> Option<f64> {
+        let dir = self.low_left_corner + u * self.hor + v * self.ver - self.origin;
+        let ray = Ray::new(&self.origin, &dir).with_time(self.shutter.0);
+        let rec = world.hit(&ray, 0.001, f64::INFINITY)?;
+
+        // Depth along the view axis, not along the ray.
+        let depth = rec.t * dir.dot(&-self.w);
+        self.set_focus_dist(depth);
+        Some(depth)
+    }
+
+    /// The camera for one eye of a stereo pair.
+    ///
+    /// Planar projections move the eye sideways and shift the image window so
+    /// that the views converge without toeing in. Equirectangular panoramas
+    /// become omni-directional stereo: each ray starts on a circle of
+    /// interocular diameter, tangent to the direction it looks in.
+    pub fn eye(&self, eye: Eye, stereo: &Stereo) -> Camera {
+        let offset = match eye {
+            Eye::Left => -stereo.interocular / 2.,
+            Eye::Right => stereo.interocular / 2.,
+        };
+        let mut cam = self.clone();
+
+        if cam.projection == Projection::Equirectangular {
+            cam.ods = Some((offset, stereo.convergence));
+        } else {
+            let shift = offset * cam.u;
+            cam.origin += shift;
+            cam.low_left_corner += (1. - cam.focus_dist / stereo.convergence) * shift;
         }
+
+        cam
     }
 
-    pub fn get_ray(&self, u: f64, v: f64) -> Ray {
-        Ray::new(
-            &self.origin,
-            &(self.low_left_corner + u * self.hor + v * self.ver - self.origin),
+    /// Ray through image coordinates `(u, v)` at a random time while the
+    /// shutter is open, or `None` when nothing is seen there. `u` and `v` run
+    /// from 0 to 1 between the outer edges of the image.
+    pub fn get_ray(&self, u: f64, v: f64, sampler: &mut Sampler) -> Option<Ray> {
+        let (open, close) = self.shutter;
+        let time = if close > open {
+            open + (close - open) * sampler.get_1d()
+        } else {
+            open
+        };
+
+        self.project(u, v, sampler).map(|ray| ray.with_time(time))
+    }
+
+    fn project(&self, u: f64, v: f64, sampler: &mut Sampler) -> Option<Ray> {
+        // Image coordinates centred on the view direction.
+        let (x, y) = (2. * u - 1., 2. * v - 1.);
+        let forward = -self.w;
+
+        let dir = match self.projection {
+            Projection::Perspective => return self.lens_ray(u, v, sampler),
+            Projection::Orthographic { height } => {
+                let aspect = self.hor.len() / self.ver.len();
+                let offset = height / 2. * (x * aspect * self.u + y * self.v);
+                return Some(Ray::new(&(self.origin + offset), &forward));
+            }
+            Projection::Fisheye { fov, mapping } => {
+                let aspect = self.hor.len() / self.ver.len();
+                let (x, y) = (x * aspect, y);
+                let r = x.hypot(y);
+                if r > 1. {
+                    return None;
+                }
+
+                let max = fov.to_radians() / 2.;
+                let theta = match mapping {
+                    FisheyeMapping::Equidistant => r * max,
+                    FisheyeMapping::Equisolid => 2. * (r * (max / 2.).sin()).asin(),
+                };
+                let radial = if r > 0. {
+                    (x / r) * self.u + (y / r) * self.v
+                } else {
+                    Vec3::default()
+                };
+                theta.sin() * radial + theta.cos() * forward
+            }
+            Projection::Equirectangular => {
+                let (phi, theta) = (x * PI, y * PI / 2.);
+                let dir =
+                    theta.cos() * (phi.sin() * self.u + phi.cos() * forward) + theta.sin() * self.v;
+
+                if let Some((offset, convergence)) = self.ods {
+                    let right = phi.cos() * self.u - phi.sin() * forward;
+                    let origin = self.origin + offset * right;
+                    let dir = if convergence.is_finite() {
+                        self.origin + convergence * dir - origin
+                    } else {
+                        dir
+                    };
+                    return Some(Ray::new(&origin, &dir));
+                }
+                dir
+            }
+            Projection::CubeFace { face } => {
+                let (front, right, up) = match face {
+                    CubeFace::Front => (forward, self.u, self.v),
+                    CubeFace::Back => (self.w, -self.u, self.v),
+                    CubeFace::Left => (-self.u, forward, self.v),
+                    CubeFace::Right => (self.u, self.w, self.v),
+                    CubeFace::Up => (self.v, self.u, self.w),
+                    CubeFace::Down => (-self.v, self.u, forward),
+                };
+                front + x * right + y * up
+            }
+        };
+
+        Some(Ray::new(&self.origin, &dir))
+    }
+
+    /// Ray from a random point on the lens through `(u, v)` on the focus
+    /// plane, or `None` when vignetting blocks that point.
+    fn lens_ray(&self, u: f64, v: f64, sampler: &mut Sampler) -> Option<Ray> {
+        let p = self.aperture.sample(sampler);
+        if self.vignetting > 0. {
+            let stop = self.vignetting * Vec3::new(2. * u - 1., 2. * v - 1., 0.);
+            if (p - stop).len_squared() > 1. {
+                return None;
+            }
+        }
+
+        let offset = self.lens_radius * (p.x / self.squeeze * self.u + p.y * self.v);
+        let origin = self.origin + offset;
+
+        Some(Ray::new(
+            &origin,
+            &(self.low_left_corner + u * self.hor + v * self.ver - origin),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        hittable::HittableList, material::Lambertian, sampler::SamplerKind, sphere::Sphere,
+    };
+
+    fn camera(aperture: f64, focus_dist: f64) -> Camera {
+        Camera::new(
+            &Point::new(0., 0., 0.),
+            &Point::new(0., 0., -1.),
+            &Vec3::new(0., 1., 0.),
+            90.,
+            2.,
+            aperture,
+            focus_dist,
+        )
+    }
+
+    fn at(ray: &Ray, z: f64) -> Point {
+        ray.at((z - ray.origin().z) / ray.dir().z)
+    }
+
+    #[test]
+    fn pinhole() {
+        let sampler = &mut Sampler::new(SamplerKind::Random, 1, 7);
+        let cam = camera(0., 5.);
+        for _ in 0..10 {
+            let ray = cam.get_ray(0.3, 0.7, sampler).unwrap();
+            assert_eq!(ray.origin().len(), 0.);
+        }
+    }
+
+    #[test]
+    fn thin_lens_converges_on_focus_plane() {
+        let sampler = &mut Sampler::new(SamplerKind::Random, 1, 7);
+        let cam = camera(2., 5.);
+        let first = cam.get_ray(0.3, 0.7, sampler).unwrap();
+        let target = at(&first, -5.);
+
+        let mut spread = 0f64;
+        for _ in 0..100 {
+            let ray = cam.get_ray(0.3, 0.7, sampler).unwrap();
+            assert!(ray.origin().z.abs() < 1e-12);
+            assert!(ray.origin().len() <= 1.);
+            assert!((at(&ray, -5.) - target).len() < 1e-9);
+            spread = spread.max((at(&ray, -10.) - at(&first, -10.)).len());
+        }
+
+        // Rays diverge again behind the focus plane.
+        assert!(spread > 0.1);
+    }
+
+    #[test]
+    fn focus_keeps_field_of_view() {
+        let sampler = &mut Sampler::new(SamplerKind::Random, 1, 7);
+        let mut cam = camera(0., 1.);
+        let before = cam.get_ray(0., 1., sampler).unwrap().dir().unit_vector();
+        cam.set_focus_dist(7.);
+        let after = cam.get_ray(0., 1., sampler).unwrap().dir().unit_vector();
+
+        assert!((before - after).len() < 1e-12);
+        assert_eq!(cam.focus_dist(), 7.);
+    }
+
+    #[test]
+    fn anamorphic_squeeze() {
+        let sampler = &mut Sampler::new(SamplerKind::Random, 1, 7);
+        let cam = camera(2., 5.).with_anamorphic_squeeze(2.);
+        for _ in 0..1000 {
+            let o = cam.get_ray(0.5, 0.5, sampler).unwrap().origin();
+            assert!(o.x.abs() <= 0.5 && o.y.abs() <= 1.);
+        }
+    }
+
+    #[test]
+    fn vignetting() {
+        let sampler = &mut Sampler::new(SamplerKind::Random, 1, 7);
+        let cam = camera(2., 5.).with_vignetting(0.8);
+        let mut blocked = 0;
+        for _ in 0..1000 {
+            assert!(cam.get_ray(0.5, 0.5, sampler).is_some());
+            match cam.get_ray(1., 1., sampler) {
+                // Only the half of the lens facing the corner stays open.
+                Some(ray) => assert!(ray.origin().x + ray.origin().y > -1e-9),
+                None => blocked += 1,
+            }
+        }
+        assert!(blocked > 300, "{blocked}");
+    }
+
+    fn projected(projection: Projection, u: f64, v: f64) -> Option<Vec3> {
+        let sampler = &mut Sampler::new(SamplerKind::Random, 1, 7);
+        let cam = Camera::new(
+            &Point::new(1., 2., 3.),
+            &Point::new(1., 2., 2.),
+            &Vec3::new(0., 1., 0.),
+            90.,
+            1.,
+            0.5,
+            1.,
         )
+        .with_projection(projection);
+        cam.get_ray(u, v, sampler).map(|r| r.dir().unit_vector())
+    }
+
+    fn assert_dir(dir: Option<Vec3>, x: f64, y: f64, z: f64) {
+        let dir = dir.unwrap();
+        assert!((dir - Vec3::new(x, y, z)).len() < 1e-9, "{dir}");
+    }
+
+    #[test]
+    fn orthographic() {
+        let sampler = &mut Sampler::new(SamplerKind::Random, 1, 7);
+        let cam = camera(2., 5.).with_projection(Projection::Orthographic { height: 4. });
+        let a = cam.get_ray(0., 0., sampler).unwrap();
+        let b = cam.get_ray(1., 1., sampler).unwrap();
+
+        assert_dir(Some(a.dir()), 0., 0., -1.);
+        assert_dir(Some(b.dir()), 0., 0., -1.);
+        assert!((a.origin() - Point::new(-4., -2., 0.)).len() < 1e-9);
+        assert!((b.origin() - Point::new(4., 2., 0.)).len() < 1e-9);
+    }
+
+    #[test]
+    fn fisheye() {
+        let s = std::f64::consts::FRAC_1_SQRT_2;
+        for mapping in [FisheyeMapping::Equidistant, FisheyeMapping::Equisolid] {
+            let fisheye = Projection::Fisheye { fov: 180., mapping };
+            assert_dir(projected(fisheye, 0.5, 0.5), 0., 0., -1.);
+            assert_dir(projected(fisheye, 1., 0.5), 1., 0., 0.);
+            assert_dir(projected(fisheye, 0.5, 0.), 0., -1., 0.);
+            assert!(projected(fisheye, 1., 1.).is_none());
+        }
+
+        // Halfway out: 45 degrees when equidistant, less when equisolid.
+        let equidistant = Projection::Fisheye {
+            fov: 180.,
+            mapping: FisheyeMapping::Equidistant,
+        };
+        assert_dir(projected(equidistant, 0.75, 0.5), s, 0., -s);
+
+        let equisolid = Projection::Fisheye {
+            fov: 180.,
+            mapping: FisheyeMapping::Equisolid,
+        };
+        let theta = 2. * (0.5 * s).asin();
+        assert_dir(
+            projected(equisolid, 0.75, 0.5),
+            theta.sin(),
+            0.,
+            -theta.cos(),
+        );
+    }
+
+    #[test]
+    fn equirectangular() {
+        let e = Projection::Equirectangular;
+        assert_dir(projected(e, 0.5, 0.5), 0., 0., -1.);
+        assert_dir(projected(e, 0.75, 0.5), 1., 0., 0.);
+        assert_dir(projected(e, 0.25, 0.5), -1., 0., 0.);
+        assert_dir(projected(e, 0., 0.5), 0., 0., 1.);
+        assert_dir(projected(e, 0.3, 1.), 0., 1., 0.);
+        assert_dir(projected(e, 0.3, 0.), 0., -1., 0.);
+    }
+
+    #[test]
+    fn cube_faces() {
+        let face = |face| Projection::CubeFace { face };
+        assert_dir(projected(face(CubeFace::Front), 0.5, 0.5), 0., 0., -1.);
+        assert_dir(projected(face(CubeFace::Back), 0.5, 0.5), 0., 0., 1.);
+        assert_dir(projected(face(CubeFace::Left), 0.5, 0.5), -1., 0., 0.);
+        assert_dir(projected(face(CubeFace::Right), 0.5, 0.5), 1., 0., 0.);
+        assert_dir(projected(face(CubeFace::Up), 0.5, 0.5), 0., 1., 0.);
+        assert_dir(projected(face(CubeFace::Down), 0.5, 0.5), 0., -1., 0.);
+
+        // Neighbouring faces meet along their shared edges.
+        let edge = |f, u, v| projected(face(f), u, v).unwrap();
+        assert!((edge(CubeFace::Front, 1., 0.5) - edge(CubeFace::Right, 0., 0.5)).len() < 1e-9);
+        assert!((edge(CubeFace::Right, 1., 0.5) - edge(CubeFace::Back, 0., 0.5)).len() < 1e-9);
+        assert!((edge(CubeFace::Front, 0.5, 1.) - edge(CubeFace::Up, 0.5, 0.)).len() < 1e-9);
+        assert!((edge(CubeFace::Front, 0.5, 0.) - edge(CubeFace::Down, 0.5, 1.)).len() < 1e-9);
+        assert!((edge(CubeFace::Left, 1., 0.5) - edge(CubeFace::Front, 0., 0.5)).len() < 1e-9);
+    }
+
+    #[test]
+    fn stereo_eyes_converge() {
+        let sampler = &mut Sampler::new(SamplerKind::Random, 1, 7);
+        let stereo = Stereo {
+            layout: StereoLayout::SideBySide,
+            interocular: 0.2,
+            convergence: 4.,
+        };
+        let cam = camera(0., 2.);
+        let left = cam.eye(Eye::Left, &stereo);
+        let right = cam.eye(Eye::Right, &stereo);
+
+        let l = left.get_ray(0.5, 0.5, sampler).unwrap();
+        let r = right.get_ray(0.5, 0.5, sampler).unwrap();
+        assert!((l.origin() - Point::new(-0.1, 0., 0.)).len() < 1e-12);
+        assert!((r.origin() - Point::new(0.1, 0., 0.)).len() < 1e-12);
+        assert!((at(&l, -4.) - Point::new(0., 0., -4.)).len() < 1e-12);
+        assert!((at(&r, -4.) - Point::new(0., 0., -4.)).len() < 1e-12);
+
+        // Parallel eyes look straight ahead.
+        let parallel = Stereo {
+            convergence: f64::INFINITY,
+            ..stereo
+        };
+        let l = cam
+            .eye(Eye::Left, &parallel)
+            .get_ray(0.5, 0.5, sampler)
+            .unwrap();
+        assert_dir(Some(l.dir().unit_vector()), 0., 0., -1.);
+    }
+
+    #[test]
+    fn omnidirectional_stereo() {
+        let sampler = &mut Sampler::new(SamplerKind::Random, 1, 7);
+        let stereo = Stereo {
+            layout: StereoLayout::OverUnder,
+            interocular: 0.2,
+            convergence: f64::INFINITY,
+        };
+        let cam = camera(0., 2.).with_projection(Projection::Equirectangular);
+        let left = cam.eye(Eye::Left, &stereo);
+        let right = cam.eye(Eye::Right, &stereo);
+
+        for i in 0..20 {
+            let u = i as f64 / 20.;
+            let l = left.get_ray(u, 0.6, sampler).unwrap();
+            let r = right.get_ray(u, 0.6, sampler).unwrap();
+
+            // Eyes sit on a circle, tangent to the viewing direction.
+            assert!((l.origin().len() - 0.1).abs() < 1e-12);
+            assert!(l.origin().dot(&l.dir()).abs() < 1e-12);
+            assert!((l.origin() + r.origin()).len() < 1e-12);
+            assert!((l.dir() - r.dir()).len() < 1e-12);
+        }
+
+        // Looking ahead the right eye is on the right, looking back on the left.
+        assert!(right.get_ray(0.5, 0.5, sampler).unwrap().origin().x > 0.);
+        assert!(right.get_ray(0., 0.5, sampler).unwrap().origin().x < 0.);
+    }
+
+    #[test]
+    fn shutter() {
+        let sampler = &mut Sampler::new(SamplerKind::Random, 1, 7);
+        let cam = camera(0., 1.);
+        assert_eq!(cam.get_ray(0.5, 0.5, sampler).unwrap().time(), 0.);
+
+        let cam = camera(0., 1.).with_shutter(0.25, 0.5);
+        let times: Vec<f64> = (0..100)
+            .map(|_| cam.get_ray(0.5, 0.5, sampler).unwrap().time())
+            .collect();
+        assert!(times.iter().all(|t| (0.25..0.5).contains(t)));
+        assert!(times.iter().any(|&t| t != times[0]));
+    }
+
+    #[test]
+    fn autofocus() {
+        let mut world = HittableList::new();
+        let mat = Arc::new(Lambertian::new(&Vec3::new(0.5, 0.5, 0.5)));
+        world.add(Sphere::new(Point::new(0., 0., -4.), 1., mat));
+
+        let mut cam = camera(0.5, 10.);
+        let depth = cam.autofocus(&world, 0.5, 0.5).unwrap();
+        assert!((depth - 3.).abs() < 1e-9);
+        assert_eq!(cam.focus_dist(), depth);
+
+        // Missing the scene leaves the focus alone.
+        assert_eq!(cam.autofocus(&world, 0., 0.), None);
+        assert_eq!(cam.focus_dist(), depth);
     }
 }
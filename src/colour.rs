@@ -0,0 +1,212 @@
+//! Colour management: the linear working space rendering happens in and the
+//! sRGB encoding of output images.
+//!
+//! Scene colours and textures are given in sRGB (linear for colour triples,
+//! encoded for 8 and 16-bit images) and converted to the working space on
+//! load. Rendered images are converted back to linear sRGB and then encoded
+//! with the sRGB transfer function.
+
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::{
+    framebuffer::Framebuffer,
+    texture::Texture,
+    vec3::{Colour, Point, Vec3},
+};
+
+/// Rows of a 3x3 matrix.
+pub(crate) type Matrix = [Vec3; 3];
+
+pub(crate) fn apply(m: &Matrix, v: &Vec3) -> Vec3 {
+    Vec3::new(m[0].dot(v), m[1].dot(v), m[2].dot(v))
+}
+
+pub(crate) fn mul(a: &Matrix, b: &Matrix) -> Matrix {
+    let col = |i| Vec3::new(b[0][i], b[1][i], b[2][i]);
+    let (c0, c1, c2) = (col(0), col(1), col(2));
+    a.map(|row| Vec3::new(row.dot(&c0), row.dot(&c1), row.dot(&c2)))
+}
+
+pub(crate) const SRGB_TO_XYZ: Matrix = [
+    Vec3::new(0.4124564, 0.3575761, 0.1804375),
+    Vec3::new(0.2126729, 0.7151522, 0.0721750),
+    Vec3::new(0.0193339, 0.1191920, 0.9503041),
+];
+
+pub(crate) const XYZ_TO_SRGB: Matrix = [
+    Vec3::new(3.2404542, -1.5371385, -0.4985314),
+    Vec3::new(-0.9692660, 1.8760108, 0.0415560),
+    Vec3::new(0.0556434, -0.2040259, 1.0572252),
+];
+
+/// Linear sRGB to ACEScg, adapting the D65 white to the ACES white by
+/// Bradford.
+const SRGB_TO_ACESCG: Matrix = [
+    Vec3::new(0.6130974, 0.3395231, 0.0473795),
+    Vec3::new(0.0701937, 0.9163539, 0.0134524),
+    Vec3::new(0.0206156, 0.1095698, 0.8698147),
+];
+
+const ACESCG_TO_SRGB: Matrix = [
+    Vec3::new(1.7050509, -0.6217921, -0.0832588),
+    Vec3::new(-0.1302564, 1.1408047, -0.0105483),
+    Vec3::new(-0.0240033, -0.1289690, 1.1529723),
+];
+
+/// Linear RGB space that light is traced in. Wider gamuts keep saturated
+/// colours from clipping as light bounces between coloured surfaces.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColourSpace {
+    /// sRGB primaries, the same as Rec. 709.
+    #[default]
+    #[serde(alias = "rec709")]
+    Srgb,
+    /// ACES AP1 primaries with the ACES white.
+    #[serde(alias = "acescg")]
+    AcesCg,
+}
+
+impl ColourSpace {
+    /// Converts a linear sRGB colour to this space.
+    pub fn from_srgb(&self, c: &Colour) -> Colour {
+        match self {
+            ColourSpace::Srgb => *c,
+            ColourSpace::AcesCg => apply(&SRGB_TO_ACESCG, c),
+        }
+    }
+
+    /// Converts a colour in this space to linear sRGB.
+    pub fn to_srgb(&self, c: &Colour) -> Colour {
+        match self {
+            ColourSpace::Srgb => *c,
+            ColourSpace::AcesCg => apply(&ACESCG_TO_SRGB, c),
+        }
+    }
+
+    /// Wraps an sRGB texture so that it returns colours in this space.
+    pub fn texture(&self, texture: Arc<dyn Texture>) -> Arc<dyn Texture> {
+        match self {
+            ColourSpace::Srgb => texture,
+            _ => Arc::new(Converted {
+                texture,
+                space: *self,
+            }),
+        }
+    }
+
+    /// Converts an image rendered in this space to linear sRGB.
+    pub fn image_to_srgb(&self, image: &mut Framebuffer) {
+        if *self != ColourSpace::Srgb {
+            for px in image.pixels_mut() {
+                *px = self.to_srgb(px);
+            }
+        }
+    }
+}
+
+struct Converted {
+    texture: Arc<dyn Texture>,
+    space: ColourSpace,
+}
+
+impl Texture for Converted {
+    fn value(&self, u: f64, v: f64, p: &Point) -> Colour {
+        self.space.from_srgb(&self.texture.value(u, v, p))
+    }
+}
+
+/// The sRGB transfer function (OETF), from linear light in `[0, 1]` to the
+/// encoded value.
+pub fn srgb_encode(c: f64) -> f64 {
+    if c <= 0.003_130_8 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1. / 2.4) - 0.055
+    }
+}
+
+/// Decodes an sRGB-encoded channel value to linear light.
+pub fn srgb_decode(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encodes a linear sRGB colour to 8 bits per channel, clipping values
+/// outside `[0, 1]`.
+pub fn to_srgb8(c: &Colour) -> [u8; 3] {
+    let encode = |c: f64| (255. * srgb_encode(c.clamp(0., 1.))).round() as u8;
+    [encode(c.x), encode(c.y), encode(c.z)]
+}
+
+/// Encodes a linear sRGB colour to 16 bits per channel, clipping values
+/// outside `[0, 1]`.
+pub fn to_srgb16(c: &Colour) -> [u16; 3] {
+    let encode = |c: f64| (65535. * srgb_encode(c.clamp(0., 1.))).round() as u16;
+    [encode(c.x), encode(c.y), encode(c.z)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::SolidColour;
+
+    #[test]
+    fn transfer_function() {
+        assert_eq!(srgb_encode(0.), 0.);
+        assert!((srgb_encode(1.) - 1.).abs() < 1e-12);
+        assert!((srgb_encode(0.214_041_140_5) - 0.5).abs() < 1e-9);
+        assert!((srgb_encode(0.001) - 0.01292).abs() < 1e-12);
+
+        // The two pieces meet, and decoding undoes encoding.
+        let knee: f64 = 0.003_130_8;
+        assert!((12.92 * knee - (1.055 * knee.powf(1. / 2.4) - 0.055)).abs() < 1e-7);
+        for i in 0..=100 {
+            let c = i as f64 / 100.;
+            assert!((srgb_decode(srgb_encode(c)) - c).abs() < 1e-12);
+        }
+
+        assert_eq!(srgb_decode(0.), 0.);
+        assert!((srgb_decode(0.5) - 0.214_041_140_5).abs() < 1e-9);
+        assert!((srgb_decode(0.04) - 0.04 / 12.92).abs() < 1e-12);
+    }
+
+    #[test]
+    fn eight_bit_values() {
+        assert_eq!(to_srgb8(&Colour::new(0., 1., 0.5)), [0, 255, 188]);
+        assert_eq!(
+            to_srgb8(&Colour::new(0.214_041_140_5, 0.18, 0.01)),
+            [128, 118, 25]
+        );
+        assert_eq!(to_srgb8(&Colour::new(-1., 7., 0.0031308)), [0, 255, 10]);
+
+        // Every code round-trips through linear light.
+        for i in 0..=255u8 {
+            let c = srgb_decode(i as f64 / 255.);
+            assert_eq!(to_srgb8(&Colour::new(c, c, c))[0], i);
+        }
+    }
+
+    #[test]
+    fn working_spaces() {
+        let red = Colour::new(1., 0., 0.);
+        let aces = ColourSpace::AcesCg.from_srgb(&red);
+        assert!((aces - Colour::new(0.6130974, 0.0701937, 0.0206156)).len() < 1e-7);
+
+        // White stays white and conversions invert each other.
+        let white = ColourSpace::AcesCg.from_srgb(&Colour::new(1., 1., 1.));
+        assert!((white - Colour::new(1., 1., 1.)).len() < 1e-6, "{white}");
+        let c = Colour::new(0.2, 0.5, 0.9);
+        let back = ColourSpace::AcesCg.to_srgb(&ColourSpace::AcesCg.from_srgb(&c));
+        assert!((back - c).len() < 1e-6);
+
+        let tex = ColourSpace::AcesCg.texture(Arc::new(SolidColour::new(&red)));
+        let v = tex.value(0., 0., &Point::default());
+        assert!((v - aces).len() < 1e-12);
+    }
+}
@@ -1,14 +1,5 @@
-use crate::vec3::{Colour, Point, Vec3};
+use crate::vec3::{Colour, Point};
 
-pub const WIDTH: i32 = 1200;
-pub const ASPECT_RATIO: f64 = 3. / 2.;
-pub const HEIGHT: i32 = (WIDTH as f64 / ASPECT_RATIO) as i32;
-pub const SAMPLES_PER_PIXEL: i64 = 500;
-pub const MAX_RECURSION: i32 = 50;
-
-pub const VIEWPORT_HEIGHT: f64 = 2.;
-pub const VIEWPORT_WIDTH: f64 = ASPECT_RATIO * VIEWPORT_HEIGHT;
-pub const FOCAL_LENGTH: f64 = 1.;
 pub const MIN_CLAMP: f64 = 0.;
 pub const MAX_CLAMP: f64 = 0.999;
 pub const MIN_DIM: f64 = 1e-8;
@@ -19,24 +10,6 @@ pub const ORIGIN: Point = Point {
     z: 0.,
 };
 
-pub const HORIZONTAL: Vec3 = Vec3 {
-    x: VIEWPORT_WIDTH,
-    y: 0.,
-    z: 0.,
-};
-
-pub const VERTICAL: Vec3 = Vec3 {
-    x: 0.,
-    y: VIEWPORT_HEIGHT,
-    z: 0.,
-};
-
-pub const DEPTH: Vec3 = Vec3 {
-    x: 0.,
-    y: 0.,
-    z: FOCAL_LENGTH,
-};
-
 pub const BLACK: Colour = Colour {
     x: 0.,
     y: 0.,
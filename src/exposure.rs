@@ -0,0 +1,252 @@
+//! Photographic exposure: f-number, shutter speed, ISO and white balance.
+
+use serde::Deserialize;
+
+use crate::{
+    colour::{apply, mul, Matrix, SRGB_TO_XYZ, XYZ_TO_SRGB},
+    framebuffer::Framebuffer,
+    vec3::{Colour, Vec3},
+};
+
+/// Camera settings in photographic terms. Radiance is taken to be luminance
+/// in cd/m² and scene units to be metres, so the defaults (f/16, 1/100 s,
+/// ISO 100) suit a sunlit scene.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Exposure {
+    #[serde(default = "default_f_number")]
+    pub f_number: f64,
+    /// Seconds the shutter stays open.
+    #[serde(default = "default_shutter_speed")]
+    pub shutter_speed: f64,
+    #[serde(default = "default_iso")]
+    pub iso: f64,
+    /// Extra exposure in stops.
+    #[serde(default)]
+    pub compensation: f64,
+    /// Colour temperature in kelvin of the light that should come out white.
+    #[serde(default = "default_white_balance")]
+    pub white_balance: f64,
+    /// Height of the film or sensor in millimetres, which with the field of
+    /// view gives the focal length.
+    #[serde(default = "default_sensor_height")]
+    pub sensor_height: f64,
+}
+
+fn default_f_number() -> f64 {
+    16.
+}
+
+fn default_shutter_speed() -> f64 {
+    1. / 100.
+}
+
+fn default_iso() -> f64 {
+    100.
+}
+
+/// The white point of sRGB, which white balance leaves unchanged.
+pub const D65_TEMPERATURE: f64 = 6504.;
+
+fn default_white_balance() -> f64 {
+    D65_TEMPERATURE
+}
+
+fn default_sensor_height() -> f64 {
+    24.
+}
+
+impl Default for Exposure {
+    fn default() -> Self {
+        Self {
+            f_number: default_f_number(),
+            shutter_speed: default_shutter_speed(),
+            iso: default_iso(),
+            compensation: 0.,
+            white_balance: default_white_balance(),
+            sensor_height: default_sensor_height(),
+        }
+    }
+}
+
+impl Exposure {
+    /// Exposure value at ISO 100.
+    pub fn ev100(&self) -> f64 {
+        (self.f_number.powi(2) / self.shutter_speed).log2() - (self.iso / 100.).log2()
+    }
+
+    /// Factor from luminance to pixel value. Following the saturation-based
+    /// sensitivity of ISO 12232, luminance `1.2 * 2^EV100` just saturates the
+    /// sensor.
+    pub fn scale(&self) -> f64 {
+        self.compensation.exp2() / (1.2 * self.ev100().exp2())
+    }
+
+    /// Focal length in millimetres giving a vertical field of view of
+    /// `vfov` degrees.
+    pub fn focal_length(&self, vfov: f64) -> f64 {
+        self.sensor_height / 2. / (vfov.to_radians() / 2.).tan()
+    }
+
+    /// Diameter of the entrance pupil in scene units (metres).
+    pub fn aperture(&self, vfov: f64) -> f64 {
+        self.focal_length(vfov) / self.f_number / 1000.
+    }
+
+    /// Exposes and white balances a rendered image.
+    pub fn apply(&self, image: &mut Framebuffer) {
+        let scale = self.scale();
+        let balance = white_balance(self.white_balance);
+        for px in image.pixels_mut() {
+            *px = scale * apply(&balance, px);
+        }
+    }
+}
+
+const BRADFORD: Matrix = [
+    Vec3::new(0.8951, 0.2664, -0.1614),
+    Vec3::new(-0.7502, 1.7135, 0.0367),
+    Vec3::new(0.0389, -0.0685, 1.0296),
+];
+
+const BRADFORD_INVERSE: Matrix = [
+    Vec3::new(0.9869929, -0.1470543, 0.1599627),
+    Vec3::new(0.4323053, 0.5183603, 0.0492912),
+    Vec3::new(-0.0085287, 0.0400428, 0.9684867),
+];
+
+/// Linear sRGB matrix turning light of colour temperature `kelvin` white,
+/// by Bradford chromatic adaptation.
+fn white_balance(kelvin: f64) -> Matrix {
+    let lms = |kelvin| apply(&BRADFORD, &white_xyz(kelvin));
+    let (to, from) = (lms(D65_TEMPERATURE), lms(kelvin));
+    let gains = [
+        Vec3::new(to.x / from.x, 0., 0.),
+        Vec3::new(0., to.y / from.y, 0.),
+        Vec3::new(0., 0., to.z / from.z),
+    ];
+
+    let adapt = mul(&BRADFORD_INVERSE, &mul(&gains, &BRADFORD));
+    mul(&XYZ_TO_SRGB, &mul(&adapt, &SRGB_TO_XYZ))
+}
+
+/// Colour of light at `kelvin` with unit luminance. This is the black body
+/// colour, nudged so that 6504 K lands exactly on the D65 white of sRGB.
+fn white_xyz(kelvin: f64) -> Vec3 {
+    const D65: (f64, f64) = (0.31271, 0.32902);
+    let (x, y) = planckian_xy(kelvin);
+    let (x65, y65) = planckian_xy(D65_TEMPERATURE);
+    let (x, y) = (x + D65.0 - x65, y + D65.1 - y65);
+
+    Vec3::new(x / y, 1., (1. - x - y) / y)
+}
+
+/// Chromaticity of a black body at `kelvin` (1667 K to 25000 K), by the
+/// cubic fit of Kim et al.
+fn planckian_xy(kelvin: f64) -> (f64, f64) {
+    let t = kelvin.clamp(1667., 25000.);
+    let (t1, t2, t3) = (1e3 / t, 1e6 / (t * t), 1e9 / (t * t * t));
+
+    let x = if t < 4000. {
+        -0.2661239 * t3 - 0.2343589 * t2 + 0.8776956 * t1 + 0.179910
+    } else {
+        -3.0258469 * t3 + 2.1070379 * t2 + 0.2226347 * t1 + 0.240390
+    };
+    let (x2, x3) = (x * x, x * x * x);
+    let y = if t < 2222. {
+        -1.1063814 * x3 - 1.34811020 * x2 + 2.18555832 * x - 0.20219683
+    } else if t < 4000. {
+        -0.9549476 * x3 - 1.37418593 * x2 + 2.09137015 * x - 0.16748867
+    } else {
+        3.0817580 * x3 - 5.87338670 * x2 + 3.75112997 * x - 0.37001483
+    };
+
+    (x, y)
+}
+
+/// Linear sRGB colour of light at `kelvin`, with unit luminance.
+pub fn illuminant(kelvin: f64) -> Colour {
+    apply(&XYZ_TO_SRGB, &white_xyz(kelvin))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        (a - b).len() < 1e-6
+    }
+
+    #[test]
+    fn exposure_value() {
+        let sunny_16 = Exposure::default();
+        assert!((sunny_16.ev100() - 14.64).abs() < 0.01);
+
+        // One stop brighter each: wider aperture, slower shutter, faster
+        // film and exposure compensation.
+        let base = Exposure {
+            f_number: 1.,
+            shutter_speed: 1.,
+            ..Exposure::default()
+        };
+        assert!((base.scale() - 1. / 1.2).abs() < 1e-12);
+        for e in [
+            Exposure {
+                f_number: 0.5f64.sqrt(),
+                ..base
+            },
+            Exposure {
+                shutter_speed: 2.,
+                ..base
+            },
+            Exposure { iso: 200., ..base },
+            Exposure {
+                compensation: 1.,
+                ..base
+            },
+        ] {
+            assert!((e.scale() / base.scale() - 2.).abs() < 1e-12, "{e:?}");
+        }
+    }
+
+    #[test]
+    fn lens() {
+        // A 50 mm lens on a full-frame sensor, wide open at f/2.
+        let vfov = 2. * (12f64 / 50.).atan().to_degrees();
+        let e = Exposure {
+            f_number: 2.,
+            ..Exposure::default()
+        };
+        assert!((e.focal_length(vfov) - 50.).abs() < 1e-9);
+        assert!((e.aperture(vfov) - 0.025).abs() < 1e-12);
+    }
+
+    #[test]
+    fn white_balance_neutralises_light() {
+        let white = Colour::new(1., 1., 1.);
+        assert!(close(apply(&white_balance(D65_TEMPERATURE), &white), white));
+
+        // Candle light is orange, and comes out grey once balanced for.
+        let candle = illuminant(1900.);
+        assert!(candle.x > candle.y && candle.y > candle.z);
+        let balanced = apply(&white_balance(1900.), &candle);
+        assert!((balanced.x - balanced.y).abs() < 1e-3, "{balanced}");
+        assert!((balanced.y - balanced.z).abs() < 1e-3, "{balanced}");
+
+        // Balancing for warm light makes neutral surfaces bluer.
+        let cool = apply(&white_balance(3200.), &white);
+        assert!(cool.z > cool.x, "{cool}");
+    }
+
+    #[test]
+    fn applies_to_image() {
+        let e = Exposure {
+            f_number: 1.,
+            shutter_speed: 1.,
+            ..Exposure::default()
+        };
+        let mut image = Framebuffer::from_pixels(1, 1, vec![Colour::new(1.2, 2.4, 0.)]);
+        e.apply(&mut image);
+        assert!(close(image.get(0, 0), Colour::new(1., 2., 0.)));
+    }
+}
@@ -0,0 +1,240 @@
+//! The film samples are splatted onto: every sample adds its radiance,
+//! weighted by the reconstruction filter, to each pixel within the filter's
+//! reach.
+
+use std::sync::Mutex;
+
+use crate::{filter::PixelFilter, framebuffer::Framebuffer, vec3::Colour};
+
+/// Net weight, as a fraction of the positive weight, below which the negative
+/// lobes of a filter have all but cancelled a pixel's samples out.
+const MIN_WEIGHT: f64 = 1e-3;
+
+/// Filter-weighted sums of the samples landing near one pixel.
+#[derive(Copy, Clone, Debug)]
+struct FilmPixel {
+    colour: Colour,
+    alpha: f64,
+    weight: f64,
+    /// The sums over only the samples with positive weights.
+    positive_colour: Colour,
+    positive_alpha: f64,
+    positive_weight: f64,
+    /// The range of the samples' radiance, per channel.
+    min: Colour,
+    max: Colour,
+}
+
+impl Default for FilmPixel {
+    fn default() -> Self {
+        Self {
+            colour: Colour::default(),
+            alpha: 0.,
+            weight: 0.,
+            positive_colour: Colour::default(),
+            positive_alpha: 0.,
+            positive_weight: 0.,
+            min: Colour::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Colour::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+}
+
+impl FilmPixel {
+    fn add(&mut self, c: Colour, alpha: f64, w: f64) {
+        self.colour += w * c;
+        self.alpha += w * alpha;
+        self.weight += w;
+        if w > 0. {
+            self.positive_colour += w * c;
+            self.positive_alpha += w * alpha;
+            self.positive_weight += w;
+        }
+        self.min = self.min.min(&c);
+        self.max = self.max.max(&c);
+    }
+
+    /// The weighted average colour and alpha. Negative lobes may sharpen it
+    /// but never push it outside the range of the samples; where they would
+    /// cancel the samples out, only the positive lobes count.
+    fn resolve(&self) -> (Colour, f64) {
+        if self.positive_weight == 0. {
+            return (Colour::default(), 0.);
+        }
+        let (colour, alpha) = if self.weight > MIN_WEIGHT * self.positive_weight {
+            (self.colour / self.weight, self.alpha / self.weight)
+        } else {
+            (
+                self.positive_colour / self.positive_weight,
+                self.positive_alpha / self.positive_weight,
+            )
+        };
+        (colour.max(&self.min).min(&self.max), alpha.clamp(0., 1.))
+    }
+}
+
+/// An image that samples can be added to from any thread. Each row has its
+/// own lock, so threads splatting onto different rows never wait for each
+/// other.
+#[derive(Debug)]
+pub struct Film {
+    width: usize,
+    height: usize,
+    filter: PixelFilter,
+    rows: Vec<Mutex<Vec<FilmPixel>>>,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize, filter: PixelFilter) -> Self {
+        Self {
+            width,
+            height,
+            filter,
+            rows: (0..height)
+                .map(|_| Mutex::new(vec![FilmPixel::default(); width]))
+                .collect(),
+        }
+    }
+
+    /// Rows, or columns, either side of a sample's own pixel that it adds
+    /// to. Zero for a box filter of radius 0.5.
+    pub fn reach(&self) -> usize {
+        (self.filter.radius() - 0.5).ceil().max(0.) as usize
+    }
+
+    /// Adds a sample of radiance `c` and coverage `alpha` taken at `(x, y)`,
+    /// in pixels from the image's top-left corner, so that pixel `(i, j)`
+    /// covers `[i, i + 1) × [j, j + 1)`.
+    pub fn add_sample(&self, (x, y): (f64, f64), c: Colour, alpha: f64) {
+        let r = self.filter.radius();
+        // Pixels with centres in `(p - r, p + r]`, where the filter reaches.
+        let range = |p: f64, len: usize| {
+            let first = ((p - 0.5 - r).floor() + 1.).max(0.) as usize;
+            let end = ((p - 0.5 + r).floor() + 1.).clamp(0., len as f64) as usize;
+            first..end
+        };
+
+        // Filters are separable, so each column's weight serves every row.
+        let columns = range(x, self.width);
+        let wx: Vec<f64> = columns
+            .clone()
+            .map(|i| self.filter.weight_1d(x - (i as f64 + 0.5)))
+            .collect();
+        for j in range(y, self.height) {
+            let wy = self.filter.weight_1d(y - (j as f64 + 0.5));
+            if wy == 0. {
+                continue;
+            }
+            let mut row = self.rows[j].lock().unwrap();
+            for (px, wx) in row[columns.clone()].iter_mut().zip(&wx) {
+                let w = wx * wy;
+                if w != 0. {
+                    px.add(c, alpha, w);
+                }
+            }
+        }
+    }
+
+    /// The weighted average of the samples around each pixel, with their
+    /// coverage as alpha. Pixels no sample reached are black and empty.
+    ///
+    /// Filters with negative lobes, such as Mitchell and Lanczos, can leave
+    /// a pixel with a net weight near zero or below it. Such pixels fall back
+    /// to the positive lobes alone, and no pixel leaves the range of the
+    /// samples that reached it.
+    pub fn into_framebuffer(self) -> Framebuffer {
+        let (pixels, alpha): (Vec<Colour>, Vec<f64>) = self
+            .rows
+            .into_iter()
+            .flat_map(|row| row.into_inner().unwrap())
+            .map(|px| px.resolve())
+            .unzip();
+        Framebuffer::from_pixels(self.width, self.height, pixels).with_alpha(alpha)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use super::*;
+    use crate::{filter::FilterKind, rng::Rng};
+
+    #[test]
+    fn box_filter_averages_each_pixel() {
+        let film = Film::new(2, 2, PixelFilter::default());
+        assert_eq!(film.reach(), 0);
+        film.add_sample((0.2, 0.7), Colour::new(1., 2., 3.), 1.);
+        film.add_sample((0.9, 0.), Colour::new(3., 2., 1.), 0.);
+        // On the corner between four pixels, it belongs to the lower right.
+        film.add_sample((1., 1.), Colour::new(5., 5., 5.), 1.);
+
+        let image = film.into_framebuffer();
+        let c = image.get(0, 0);
+        assert_eq!((c.x, c.y, c.z), (2., 2., 2.));
+        assert_eq!(image.get(1, 1).x, 5.);
+        assert_eq!(image.get(1, 0).x, 0.);
+        assert_eq!(image.alpha().unwrap(), [0.5, 0., 0., 1.]);
+    }
+
+    #[test]
+    fn wider_filters_reach_neighbours() {
+        let tent = PixelFilter::new(FilterKind::Tent).with_radius(1.5);
+        let film = Film::new(3, 1, tent);
+        assert_eq!(film.reach(), 1);
+        film.add_sample((0.5, 0.5), Colour::new(1., 1., 1.), 1.);
+        film.add_sample((2.5, 0.5), Colour::new(4., 4., 4.), 1.);
+
+        // The middle pixel is 1 away from both, so takes their mean; the
+        // others mostly keep their own sample.
+        let image = film.into_framebuffer();
+        let x: Vec<f64> = (0..3).map(|i| image.get(i, 0).x).collect();
+        assert!((x[1] - 2.5).abs() < 1e-12, "{x:?}");
+        assert!(x[0] < 1.2 && x[2] > 3.8, "{x:?}");
+    }
+
+    #[test]
+    fn samples_from_many_threads() {
+        let gaussian = PixelFilter::new(FilterKind::Gaussian);
+        let film = Film::new(8, 8, gaussian);
+        (0..64 * 64).into_par_iter().for_each(|n| {
+            let (x, y) = ((n % 64) as f64 / 8., (n / 64) as f64 / 8.);
+            film.add_sample((x, y), Colour::new(0.25, 0.5, 1.), 1.);
+        });
+
+        let image = film.into_framebuffer();
+        for c in image.pixels() {
+            assert!((c.x - 0.25).abs() < 1e-12 && (c.z - 1.).abs() < 1e-12);
+        }
+        assert!(image.alpha().unwrap().iter().all(|&a| a == 1.));
+    }
+
+    #[test]
+    fn negative_lobes_stay_within_the_samples() {
+        for kind in [FilterKind::Mitchell, FilterKind::Lanczos] {
+            let film = Film::new(16, 16, PixelFilter::new(kind));
+            let mut rng = Rng::new(7, 0);
+            let (mut lo, mut hi) = (f64::INFINITY, f64::NEG_INFINITY);
+            // One jittered sample per pixel, as at 1 spp.
+            for j in 0..16 {
+                for i in 0..16 {
+                    let (x, y) = (i as f64 + rng.random(), j as f64 + rng.random());
+                    let v = rng.random();
+                    lo = lo.min(v);
+                    hi = hi.max(v);
+                    film.add_sample((x, y), Colour::new(v, v, v), 1.);
+                }
+            }
+
+            let image = film.into_framebuffer();
+            for c in image.pixels() {
+                assert!(
+                    (lo..=hi).contains(&c.x),
+                    "{kind:?}: {} not in [{lo}, {hi}]",
+                    c.x
+                );
+            }
+            assert!(image.alpha().unwrap().iter().all(|&a| a == 1.));
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Pixel reconstruction filters, which weight each sample by its distance
+//! from the centre of every pixel within reach.
+
+use std::f64::consts::PI;
+
+use serde::Deserialize;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterKind {
+    /// Equal weights out to the radius. With a radius of half a pixel, the
+    /// plain average of the pixel's own samples.
+    #[default]
+    Box,
+    /// Weights falling linearly to zero at the radius.
+    Tent,
+    /// A Gaussian with a standard deviation of a third of the radius,
+    /// shifted down to reach zero there.
+    Gaussian,
+    /// The Mitchell–Netravali cubic with B = C = 1/3, stretched over the
+    /// radius. Its small negative lobes keep edges sharp.
+    Mitchell,
+    /// A sinc windowed by a wider sinc, with as many lobes as pixels of
+    /// radius. Sharpest, but may ring around bright edges.
+    Lanczos,
+    /// The four-term Blackman–Harris window: about as smooth as a Gaussian
+    /// but sharper.
+    BlackmanHarris,
+}
+
+impl FilterKind {
+    /// Radius used when none is given, in pixels.
+    pub fn default_radius(self) -> f64 {
+        match self {
+            FilterKind::Box => 0.5,
+            FilterKind::Tent => 1.,
+            FilterKind::Gaussian | FilterKind::BlackmanHarris => 1.5,
+            FilterKind::Mitchell => 2.,
+            FilterKind::Lanczos => 3.,
+        }
+    }
+}
+
+/// A filter and how far it reaches.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PixelFilter {
+    #[serde(rename = "type")]
+    pub kind: FilterKind,
+    /// In pixels. The kind's default when `None`.
+    pub radius: Option<f64>,
+}
+
+impl PixelFilter {
+    pub fn new(kind: FilterKind) -> Self {
+        Self { kind, radius: None }
+    }
+
+    pub fn with_radius(mut self, radius: f64) -> Self {
+        self.radius = Some(radius);
+        self
+    }
+
+    pub fn radius(&self) -> f64 {
+        self.radius.unwrap_or_else(|| self.kind.default_radius())
+    }
+
+    /// Weight of a sample `(dx, dy)` pixels from a pixel's centre. Filters
+    /// are separable, the product of the weights along each axis, and cover
+    /// `[-radius, radius)` so that a sample on the edge between two pixels
+    /// counts for only one of them.
+    pub fn weight(&self, dx: f64, dy: f64) -> f64 {
+        self.weight_1d(dx) * self.weight_1d(dy)
+    }
+
+    /// Weight along one axis, `d` pixels from a pixel's centre.
+    pub fn weight_1d(&self, d: f64) -> f64 {
+        let r = self.radius();
+        if d < -r || d >= r {
+            return 0.;
+        }
+        let d = d.abs();
+
+        match self.kind {
+            FilterKind::Box => 1.,
+            FilterKind::Tent => 1. - d / r,
+            FilterKind::Gaussian => {
+                let sigma = r / 3.;
+                let gaussian = |x: f64| (-x * x / (2. * sigma * sigma)).exp();
+                gaussian(d) - gaussian(r)
+            }
+            FilterKind::Mitchell => mitchell(2. * d / r),
+            FilterKind::Lanczos => sinc(d) * sinc(d / r),
+            FilterKind::BlackmanHarris => {
+                let t = 2. * PI * (d + r) / (2. * r);
+                0.35875 - 0.48829 * t.cos() + 0.14128 * (2. * t).cos() - 0.01168 * (3. * t).cos()
+            }
+        }
+    }
+}
+
+/// Mitchell–Netravali cubic with B = C = 1/3, over `[0, 2)`.
+fn mitchell(x: f64) -> f64 {
+    const B: f64 = 1. / 3.;
+    const C: f64 = 1. / 3.;
+    let (x2, x3) = (x * x, x * x * x);
+    if x < 1. {
+        ((12. - 9. * B - 6. * C) * x3 + (-18. + 12. * B + 6. * C) * x2 + (6. - 2. * B)) / 6.
+    } else {
+        ((-B - 6. * C) * x3
+            + (6. * B + 30. * C) * x2
+            + (-12. * B - 48. * C) * x
+            + (8. * B + 24. * C))
+            / 6.
+    }
+}
+
+/// Normalised sinc, `sin(πx) / πx`.
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        1.
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [FilterKind; 6] = [
+        FilterKind::Box,
+        FilterKind::Tent,
+        FilterKind::Gaussian,
+        FilterKind::Mitchell,
+        FilterKind::Lanczos,
+        FilterKind::BlackmanHarris,
+    ];
+
+    /// Integral of the weights along one axis.
+    fn area(filter: &PixelFilter) -> f64 {
+        let r = filter.radius();
+        let n = 10_000;
+        let dx = 2. * r / n as f64;
+        (0..n)
+            .map(|i| filter.weight_1d(-r + (i as f64 + 0.5) * dx) * dx)
+            .sum()
+    }
+
+    #[test]
+    fn symmetric_and_zero_past_radius() {
+        for kind in KINDS {
+            for filter in [
+                PixelFilter::new(kind),
+                PixelFilter::new(kind).with_radius(1.2),
+            ] {
+                let r = filter.radius();
+                assert!(filter.weight(0., 0.) > 0., "{kind:?}");
+                assert_eq!(filter.weight(r, 0.), 0., "{kind:?}");
+                assert_eq!(filter.weight(0., -r - 0.1), 0., "{kind:?}");
+                for d in [0.1, 0.3, 0.7, 1.1] {
+                    let (a, b) = (filter.weight(d, 0.2), filter.weight(-d, -0.2));
+                    assert!((a - b).abs() < 1e-12, "{kind:?} {d}");
+                }
+                // Smooth filters fade out rather than stopping dead.
+                if kind != FilterKind::Box {
+                    assert!(filter.weight(0.999 * r, 0.).abs() < 1e-2, "{kind:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn known_shapes() {
+        let tent = PixelFilter::new(FilterKind::Tent).with_radius(2.);
+        assert!((tent.weight(1., 0.) - 0.5).abs() < 1e-12);
+        assert!((area(&tent) - 2.).abs() < 1e-6);
+
+        // Mitchell–Netravali is normalised over its natural radius of 2 and
+        // dips below zero between 1 and 2 pixels out.
+        let mitchell = PixelFilter::new(FilterKind::Mitchell);
+        assert!((area(&mitchell) - 1.).abs() < 1e-6);
+        assert!((mitchell.weight(0., 0.) - (8. / 9.) * (8. / 9.)).abs() < 1e-12);
+        assert!(mitchell.weight(1.5, 0.) < 0.);
+
+        // Lanczos is zero at every whole pixel but the centre.
+        let lanczos = PixelFilter::new(FilterKind::Lanczos);
+        assert_eq!(lanczos.weight(0., 0.), 1.);
+        for d in [1., 2.] {
+            assert!(lanczos.weight(d, 0.).abs() < 1e-12);
+        }
+        assert!(lanczos.weight(1.5, 0.) < 0.);
+
+        let bh = PixelFilter::new(FilterKind::BlackmanHarris);
+        assert!((bh.weight(0., 0.) - 1.).abs() < 1e-12);
+    }
+}
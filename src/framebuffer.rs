@@ -0,0 +1,122 @@
+use crate::vec3::Colour;
+
+/// Linear floating-point image, stored row by row from the top-left pixel,
+/// with an optional alpha channel and count of the samples behind each
+/// pixel. Images without alpha are opaque.
+#[derive(Clone, Debug)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Colour>,
+    alpha: Option<Vec<f64>>,
+    sample_counts: Option<Vec<u64>>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Colour::default(); width * height],
+            alpha: None,
+            sample_counts: None,
+        }
+    }
+
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Colour>) -> Self {
+        assert_eq!(pixels.len(), width * height, "pixel count mismatch");
+        Self {
+            width,
+            height,
+            pixels,
+            alpha: None,
+            sample_counts: None,
+        }
+    }
+
+    pub fn with_alpha(mut self, alpha: Vec<f64>) -> Self {
+        assert_eq!(alpha.len(), self.pixels.len(), "alpha count mismatch");
+        self.alpha = Some(alpha);
+        self
+    }
+
+    pub fn alpha(&self) -> Option<&[f64]> {
+        self.alpha.as_deref()
+    }
+
+    pub fn with_sample_counts(mut self, counts: Vec<u64>) -> Self {
+        assert_eq!(counts.len(), self.pixels.len(), "sample count mismatch");
+        self.sample_counts = Some(counts);
+        self
+    }
+
+    pub fn sample_counts(&self) -> Option<&[u64]> {
+        self.sample_counts.as_deref()
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Colour {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[Colour] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [Colour] {
+        &mut self.pixels
+    }
+
+    /// Copies `other` into this image with its top-left corner at `(x, y)`.
+    /// Pasting an image with alpha or sample counts gives this one them too.
+    pub fn paste(&mut self, other: &Framebuffer, x: usize, y: usize) {
+        assert!(
+            x + other.width <= self.width && y + other.height <= self.height,
+            "pasted image does not fit"
+        );
+        let len = self.pixels.len();
+        copy_rect(
+            &mut self.pixels,
+            self.width,
+            &other.pixels,
+            other.width,
+            x,
+            y,
+        );
+        if let Some(src) = &other.alpha {
+            let alpha = self.alpha.get_or_insert_with(|| vec![1.; len]);
+            copy_rect(alpha, self.width, src, other.width, x, y);
+        }
+        if let Some(src) = &other.sample_counts {
+            let counts = self.sample_counts.get_or_insert_with(|| vec![0; len]);
+            copy_rect(counts, self.width, src, other.width, x, y);
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, c: Colour) {
+        self.pixels[y * self.width + x] = c;
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+}
+
+/// Copies the rows of `src`, `src_width` wide, into `dst`, `dst_width` wide,
+/// with the top-left corner at `(x, y)`.
+fn copy_rect<T: Copy>(
+    dst: &mut [T],
+    dst_width: usize,
+    src: &[T],
+    src_width: usize,
+    x: usize,
+    y: usize,
+) {
+    for (row, src) in src.chunks(src_width).enumerate() {
+        let start = (y + row) * dst_width + x;
+        dst[start..start + src_width].copy_from_slice(src);
+    }
+}
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use crate::{
+    aabb::Aabb,
     material::Material,
     ray::Ray,
     vec3::{Point, Vec3},
@@ -12,6 +13,8 @@ pub struct HitRecord {
     pub mat: Arc<dyn Material>,
     pub n: Vec3,
     pub front_face: bool,
+    pub u: f64,
+    pub v: f64,
 }
 
 impl HitRecord {
@@ -22,6 +25,8 @@ impl HitRecord {
             n: Vec3::new(0., 0., 0.),
             mat: mat.clone(),
             front_face: true,
+            u: 0.,
+            v: 0.,
         }
     }
 
@@ -37,6 +42,19 @@ impl HitRecord {
 
 pub trait Hittable: Sync + Send {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+
+    /// Box enclosing the object, or `None` for unbounded objects (e.g. planes).
+    fn bounding_box(&self) -> Option<Aabb>;
+}
+
+impl<T: Hittable + ?Sized> Hittable for Box<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        (**self).hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        (**self).bounding_box()
+    }
 }
 
 #[derive(Default)]
@@ -73,4 +91,11 @@ impl Hittable for HittableList {
 
         is_hit
     }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let mut objects = self.objects.iter();
+        let first = objects.next()?.bounding_box()?;
+
+        objects.try_fold(first, |bbox, obj| Some(bbox.union(&obj.bounding_box()?)))
+    }
 }
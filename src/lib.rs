@@ -1,7 +1,32 @@
+pub mod aabb;
+pub mod adaptive;
+pub mod animation;
+pub mod aperture;
+pub mod background;
+pub mod bvh;
 pub mod camera;
+pub mod colour;
 pub mod constants;
+pub mod exposure;
+pub mod film;
+pub mod filter;
+pub mod framebuffer;
 pub mod hittable;
 pub mod material;
+pub mod mesh;
+pub mod motion;
+pub mod noise;
+pub mod obj;
+pub mod output;
 pub mod ray;
+pub mod render;
+pub mod rng;
+pub mod sampler;
+pub mod scene;
+pub mod settings;
 pub mod sphere;
+pub mod texture;
+pub mod tonemap;
+pub mod transform;
+pub mod triangle;
 pub mod vec3;
@@ -2,46 +2,249 @@ use std::{
     error::Error,
     fs::File,
     io::{BufWriter, Write},
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
-use rand::Rng;
-use rayon::prelude::*;
+use clap::Parser;
 use raytracer::{
+    adaptive::{sample_map, AdaptiveSampling},
+    animation::frame_path,
+    background::Background,
+    bvh::Bvh,
     camera::Camera,
-    hittable::{Hittable, HittableList},
+    filter::{FilterKind, PixelFilter},
+    framebuffer::Framebuffer,
+    hittable::HittableList,
     material::{Dielectric, Lambertian, Metal},
-    ray::Ray,
+    output::{write_exr, write_image, BitDepth, ImageFormat, OutputError, Precision},
+    render::Renderer,
+    rng::Rng,
+    sampler::SamplerKind,
+    scene::{load_scene, Scene},
+    settings::RenderSettings,
     sphere::Sphere,
-    vec3::{Colour, Point, Vec3},
+    tonemap::ToneMapping,
+    vec3::{Colour, Point},
 };
 
-use raytracer::constants::*;
+/// Renders a scene to a PNG, PPM, OpenEXR or PFM image, or a numbered sequence of them.
+#[derive(Parser)]
+#[command(version, about)]
+struct Args {
+    /// TOML scene description. Renders the random spheres scene when omitted.
+    scene: Option<PathBuf>,
 
-fn get_colour(ray: Ray, world: &HittableList, recursion_depth: i32) -> Colour {
-    if recursion_depth <= 0 {
-        return BLACK;
-    }
+    /// Image width in pixels. The height follows the scene's aspect ratio unless given too.
+    #[arg(short = 'W', long, value_parser = clap::value_parser!(i32).range(1..))]
+    width: Option<i32>,
+
+    /// Image height in pixels. The width follows the scene's aspect ratio unless given too.
+    #[arg(short = 'H', long, value_parser = clap::value_parser!(i32).range(1..))]
+    height: Option<i32>,
+
+    /// Samples per pixel.
+    #[arg(short, long, value_parser = clap::value_parser!(i64).range(1..))]
+    samples: Option<i64>,
+
+    /// Keep sampling pixels whose relative standard error is above this.
+    #[arg(long, value_name = "THRESHOLD", value_parser = parse_positive)]
+    adaptive: Option<f64>,
+
+    /// Most samples per pixel with adaptive sampling.
+    #[arg(long, requires = "adaptive", value_parser = clap::value_parser!(i64).range(1..))]
+    max_samples: Option<i64>,
+
+    /// Maximum number of bounces per path.
+    #[arg(short = 'd', long, value_parser = clap::value_parser!(i32).range(1..))]
+    max_depth: Option<i32>,
+
+    /// Output image path. The extension picks the format: .png, .ppm, .exr or .pfm.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Also write an image of the samples each pixel took.
+    #[arg(long)]
+    sample_map: Option<PathBuf>,
+
+    /// Bits per channel of the output image, 8 or 16.
+    #[arg(long, value_parser = parse_bit_depth)]
+    bit_depth: Option<BitDepth>,
+
+    /// Sample type of OpenEXR output, half or float.
+    #[arg(long, value_parser = parse_precision)]
+    precision: Option<Precision>,
+
+    /// Sample generator: random, stratified, halton, sobol or blue_noise.
+    #[arg(long, value_parser = parse_sampler)]
+    sampler: Option<SamplerKind>,
+
+    /// Reconstruction filter: box, tent, gaussian, mitchell, lanczos or blackman_harris.
+    #[arg(long, value_parser = parse_filter)]
+    filter: Option<FilterKind>,
+
+    /// Reconstruction filter radius in pixels. Defaults to the filter's own.
+    #[arg(long, value_parser = parse_positive)]
+    filter_radius: Option<f64>,
+
+    /// Seed for reproducible renders, and for the random spheres scene.
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Focus on whatever is visible through this pixel, counted from the top-left corner.
+    #[arg(long, num_args = 2, value_names = ["X", "Y"])]
+    focus_at: Option<Vec<u32>>,
+}
+
+impl Args {
+    /// Overrides the scene's settings with the ones given on the command line.
+    fn apply(&self, scene: &mut Scene) -> Result<(), Box<dyn Error>> {
+        let (settings, camera) = (&mut scene.settings, &mut scene.camera);
+        let (width, height) = (settings.width, settings.height);
+        let aspect_ratio = settings.aspect_ratio();
+        match (self.width, self.height) {
+            (Some(w), Some(h)) => (settings.width, settings.height) = (w, h),
+            (Some(w), None) => {
+                (settings.width, settings.height) = (w, aspect_height(w, aspect_ratio))
+            }
+            (None, Some(h)) => {
+                (settings.width, settings.height) = (aspect_width(h, aspect_ratio), h)
+            }
+            (None, None) => {}
+        }
+        camera.set_aspect_ratio(settings.aspect_ratio());
 
-    if let Some(rec) = world.hit(&ray, 0.001, f64::INFINITY) {
-        let mut attenuation = WHITE;
-        if let Some(scattered_ray) = rec.mat.scatter(&ray, &rec, &mut attenuation) {
-            return attenuation * get_colour(scattered_ray, world, recursion_depth - 1);
+        // The scene's focus pixel keeps its place in the resized frame.
+        let rescale =
+            |x: u32, from: i32, to: i32| ((x as f64 + 0.5) * to as f64 / from as f64) as u32;
+        scene.focus_pixel = match self.focus_at.as_deref() {
+            Some(&[x, y]) => Some((x, y)),
+            _ => scene.focus_pixel.map(|(x, y)| {
+                (
+                    rescale(x, width, settings.width),
+                    rescale(y, height, settings.height),
+                )
+            }),
+        };
+        if let Some((x, y)) = scene.focus_pixel {
+            if x as i32 >= settings.width || y as i32 >= settings.height {
+                return Err(format!(
+                    "focus pixel ({x}, {y}) lies outside the {}x{} image",
+                    settings.width, settings.height
+                )
+                .into());
+            }
+        }
+        if let Some(stereo) = &scene.stereo {
+            if !stereo.fits(settings.width, settings.height) {
+                return Err(format!(
+                    "a {}x{} image is too small to split into a stereo pair",
+                    settings.width, settings.height
+                )
+                .into());
+            }
+        }
+
+        if let Some(samples) = self.samples {
+            settings.samples_per_pixel = samples;
+        }
+        if let Some(threshold) = self.adaptive {
+            let adaptive = settings
+                .adaptive
+                .get_or_insert(AdaptiveSampling::new(threshold));
+            adaptive.threshold = threshold;
+        }
+        if let (Some(max), Some(adaptive)) = (self.max_samples, &mut settings.adaptive) {
+            adaptive.max_samples_per_pixel = Some(max);
+        }
+        if let Some(depth) = self.max_depth {
+            settings.max_depth = depth;
+        }
+        if let Some(output) = &self.output {
+            settings.output = output.clone();
+        }
+        if let Some(map) = &self.sample_map {
+            settings.sample_map = Some(map.clone());
         }
+        if let Some(depth) = self.bit_depth {
+            settings.bit_depth = depth;
+        }
+        if let Some(precision) = self.precision {
+            settings.precision = precision;
+        }
+        if let Some(sampler) = self.sampler {
+            settings.sampler = sampler;
+        }
+        if let Some(kind) = self.filter {
+            settings.filter = PixelFilter::new(kind);
+        }
+        if let Some(radius) = self.filter_radius {
+            settings.filter.radius = Some(radius);
+        }
+        if let Some(seed) = self.seed {
+            settings.seed = Some(seed);
+        }
+        Ok(())
+    }
+}
 
-        return BLACK;
+fn parse_positive(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(x) if x > 0. => Ok(x),
+        Ok(_) => Err("must be positive".into()),
+        Err(e) => Err(format!("{e}")),
     }
+}
 
-    let unit_dir = ray.dir().unit_vector();
-    let t = 0.5 * (unit_dir.y + 1.);
-    (1. - t) * WHITE + t * BLUE
+fn parse_bit_depth(s: &str) -> Result<BitDepth, String> {
+    let bits: u8 = s.parse().map_err(|e| format!("{e}"))?;
+    BitDepth::try_from(bits)
 }
 
-fn random_f64() -> f64 {
-    rand::thread_rng().gen_range(0.0..1.)
+fn parse_precision(s: &str) -> Result<Precision, String> {
+    match s {
+        "half" => Ok(Precision::Half),
+        "float" => Ok(Precision::Float),
+        _ => Err(format!("expected half or float, not {s}")),
+    }
 }
 
-fn generate_random_scene() -> HittableList {
+fn parse_sampler(s: &str) -> Result<SamplerKind, String> {
+    match s {
+        "random" => Ok(SamplerKind::Random),
+        "stratified" => Ok(SamplerKind::Stratified),
+        "halton" => Ok(SamplerKind::Halton),
+        "sobol" => Ok(SamplerKind::Sobol),
+        "blue_noise" => Ok(SamplerKind::BlueNoise),
+        _ => Err(format!(
+            "expected random, stratified, halton, sobol or blue_noise, not {s}"
+        )),
+    }
+}
+
+fn parse_filter(s: &str) -> Result<FilterKind, String> {
+    match s {
+        "box" => Ok(FilterKind::Box),
+        "tent" => Ok(FilterKind::Tent),
+        "gaussian" => Ok(FilterKind::Gaussian),
+        "mitchell" => Ok(FilterKind::Mitchell),
+        "lanczos" => Ok(FilterKind::Lanczos),
+        "blackman_harris" => Ok(FilterKind::BlackmanHarris),
+        _ => Err(format!(
+            "expected box, tent, gaussian, mitchell, lanczos or blackman_harris, not {s}"
+        )),
+    }
+}
+
+fn aspect_height(width: i32, aspect_ratio: f64) -> i32 {
+    ((width as f64 / aspect_ratio) as i32).max(1)
+}
+
+fn aspect_width(height: i32, aspect_ratio: f64) -> i32 {
+    ((height as f64 * aspect_ratio) as i32).max(1)
+}
+
+fn generate_random_scene(rng: &mut Rng) -> HittableList {
     let mut world = HittableList::new();
 
     let ground = Arc::new(Lambertian::new(&Colour::new(0.5, 0.5, 0.5)));
@@ -49,20 +252,20 @@ fn generate_random_scene() -> HittableList {
 
     for a in -11..11 {
         for b in -11..11 {
-            let mat = random_f64();
+            let mat = rng.random();
             let centre = Point::new(
-                a as f64 + 0.9 * random_f64(),
+                a as f64 + 0.9 * rng.random(),
                 0.2,
-                b as f64 + 0.9 * random_f64(),
+                b as f64 + 0.9 * rng.random(),
             );
             if (centre - Point::new(4., 0.2, 0.)).len() > 0.9 {
                 if mat < 0.8 {
-                    let albedo = Colour::random() * Colour::random();
+                    let albedo = Colour::random(rng) * Colour::random(rng);
                     let material = Arc::new(Lambertian::new(&albedo));
                     world.add(Sphere::new(centre, 0.2, material));
                 } else if mat < 0.95 {
-                    let albedo = Colour::random_bounded(0.5, 1.);
-                    let fuzz = rand::thread_rng().gen_range(0.0..0.5);
+                    let albedo = Colour::random_bounded(rng, 0.5, 1.);
+                    let fuzz = rng.random_range(0., 0.5);
                     let material = Arc::new(Metal::new(&albedo, fuzz));
                     world.add(Sphere::new(centre, 0.2, material));
                 } else {
@@ -84,79 +287,138 @@ fn generate_random_scene() -> HittableList {
     world
 }
 
-fn hit_sphere(centre: &Point, radius: f64, ray: &Ray) -> f64 {
-    // Equation for sphere intersection with the ray
-    //
-    // t^2 b*b + t 2b*(O-C) + (O-C)*(O-C) -r^2 = 0
-    //
-    // b: direction vector
-    // O: ray origin
-    // C: sphere centre
-    // r: sphere radius
-    // t: unknown. ray parameter. Ray = O + t*b
-
-    let oc = ray.origin() - *centre;
-    let d = ray.dir();
-
-    let a = d.len_squared();
-    let half_b = d.dot(&oc);
-    let c = oc.dot(&oc) - radius * radius;
-    let delta = half_b * half_b - a * c;
-
-    if delta < 0. {
-        return -1.;
-    }
-
-    (-half_b - delta.sqrt()) / a
-}
-
-fn main() -> Result<(), Box<dyn Error>> {
-    let world = generate_random_scene();
+fn default_scene(seed: Option<u64>) -> Scene {
+    let settings = RenderSettings::default();
+    let mut rng = Rng::new(seed.unwrap_or_else(rand::random), 0);
 
     let from = Point::new(13., 2., 3.);
     let to = Point::new(0., 0., 0.);
     let vup = Point::new(0., 1., 0.);
     let focus_dist = 10.;
     let aperture = 0.1;
-    let cam = Camera::new(&from, &to, &vup, 20., ASPECT_RATIO, aperture, focus_dist);
-
-    let file = File::create("img.ppm")?;
-    let log = File::create("raytracer.log")?;
-    let mut w = BufWriter::new(file);
-    let mut l = BufWriter::new(log);
-    w.write_all(b"P3\n")?;
-    w.write_all(format!("{WIDTH} {HEIGHT}\n").as_bytes())?;
-    w.write_all(b"255\n")?;
-
-    let image = (0..HEIGHT)
-        .into_par_iter()
-        .rev()
-        .flat_map(|j| {
-            // println!("  Writing height level {j}");
-            (0..WIDTH)
-                .flat_map(|i| {
-                    let px_colour: Vec3 = (0..SAMPLES_PER_PIXEL)
-                        .map(|_| {
-                            let mut rng = rand::thread_rng();
-                            let u = (i as f64 + rng.gen_range(0.0..1.)) / ((WIDTH - 1) as f64);
-                            let v = (j as f64 + rng.gen_range(0.0..1.)) / ((HEIGHT - 1) as f64);
-                            let ray = cam.get_ray(u, v);
-
-                            // println!("      Writing sample {s}");
-                            get_colour(ray, &world, MAX_RECURSION)
-                        })
-                        .sum();
-                    // println!("    Writing width level {i}");
-                    px_colour.as_colour_bytes()
-                })
-                .collect::<Vec<u8>>()
-        })
-        .collect::<Vec<u8>>();
-
-    for nums in image.chunks(3) {
-        w.write_all(format!("{} {} {}\n", nums[0], nums[1], nums[2]).as_bytes())?;
+    let camera = Camera::new(
+        &from,
+        &to,
+        &vup,
+        20.,
+        settings.aspect_ratio(),
+        aperture,
+        focus_dist,
+    );
+
+    Scene {
+        world: generate_random_scene(&mut rng),
+        camera,
+        settings,
+        background: Background::default(),
+        focus_pixel: None,
+        stereo: None,
+        animation: None,
+        exposure: None,
+        tone_mapping: ToneMapping::default(),
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let mut scene = match &args.scene {
+        Some(path) => load_scene(path)?,
+        None => default_scene(args.seed),
+    };
+    args.apply(&mut scene)?;
+
+    // Fail before rendering rather than after.
+    ImageFormat::from_path(&scene.settings.output)?;
+    if let Some(map) = &scene.settings.sample_map {
+        ImageFormat::from_path(map)?;
+    }
+
+    let world = Bvh::new(scene.world);
+    let focus = |camera: &mut Camera| {
+        if let Some((x, y)) = scene.focus_pixel {
+            let (u, v) = scene.settings.pixel_uv(x, y);
+            if camera.autofocus(&world, u, v).is_none() {
+                eprintln!("warning: nothing to focus on at pixel ({x}, {y})");
+            }
+        }
+    };
+
+    let mut renderer = Renderer::new(scene.settings.clone());
+    renderer.background = scene.background;
+    let render = |renderer: &Renderer, camera: &Camera| {
+        let mut image = match &scene.stereo {
+            Some(stereo) => renderer.render_stereo(&world, camera, stereo),
+            None => renderer.render(&world, camera),
+        };
+        scene.settings.colour_space.image_to_srgb(&mut image);
+        if let Some(exposure) = &scene.exposure {
+            exposure.apply(&mut image);
+        }
+        image
+    };
+    // Float formats keep the linear image, the others are tone mapped first.
+    let save = |mut image: Framebuffer, frame: Option<u32>| -> Result<(), OutputError> {
+        let settings = &scene.settings;
+        let frame_path = |path: &Path| match frame {
+            Some(frame) => frame_path(path, frame),
+            None => path.to_path_buf(),
+        };
+        if let Some(map_path) = &settings.sample_map {
+            let map_path = frame_path(map_path);
+            // Raw counts in float formats, at a precision that keeps them
+            // exact, and scaled to the most samples otherwise.
+            let scale = match ImageFormat::from_path(&map_path)?.is_float() {
+                true => 1.,
+                false => image
+                    .sample_counts()
+                    .into_iter()
+                    .flatten()
+                    .max()
+                    .map_or(1, |&n| n.max(1)) as f64,
+            };
+            if let Some(map) = sample_map(&image, scale) {
+                write_image(&map, &map_path, settings.bit_depth, Precision::Float)?;
+            }
+        }
+
+        let path = &frame_path(&settings.output);
+        match ImageFormat::from_path(path)? {
+            ImageFormat::Exr if settings.display_layer => {
+                let mut display = image.clone();
+                scene.tone_mapping.apply(&mut display);
+                let layers = [("", &image), ("display", &display)];
+                write_exr(&layers, path, settings.precision)
+            }
+            ImageFormat::Exr | ImageFormat::Pfm => {
+                write_image(&image, path, settings.bit_depth, settings.precision)
+            }
+            _ => {
+                scene.tone_mapping.apply(&mut image);
+                write_image(&image, path, settings.bit_depth, settings.precision)
+            }
+        }
+    };
+
+    match &scene.animation {
+        None => {
+            let mut camera = scene.camera.clone();
+            focus(&mut camera);
+            save(render(&renderer, &camera), None)?;
+        }
+        Some(animation) => {
+            for frame in 0..animation.frames {
+                let mut camera = animation.camera(&scene.camera, frame);
+                focus(&mut camera);
+                renderer.settings.seed = animation.seed(scene.settings.seed, frame);
+
+                save(render(&renderer, &camera), Some(frame))?;
+                let path = frame_path(&scene.settings.output, frame);
+                eprintln!("wrote {}", path.display());
+            }
+        }
     }
 
+    let mut l = BufWriter::new(File::create("raytracer.log")?);
     l.write_all(b"Done.")?;
     Ok(())
 }
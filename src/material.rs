@@ -1,60 +1,96 @@
+use std::sync::Arc;
+
 use crate::{
-    constants::WHITE,
+    constants::{BLACK, WHITE},
     hittable::HitRecord,
     ray::Ray,
-    vec3::{Colour, Vec3},
+    sampler::{self, Sampler},
+    texture::{SolidColour, Texture},
+    vec3::Colour,
 };
 
-use rand::Rng;
-
 pub trait Material: Sync + Send {
-    fn scatter(&self, ray: &Ray, rec: &HitRecord, attenuation: &mut Colour) -> Option<Ray>;
+    /// Picks the direction light arriving along `ray` leaves in, drawing any
+    /// sample values from `sampler`. Returns `None` when it is absorbed.
+    fn scatter(
+        &self,
+        ray: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Colour,
+        sampler: &mut Sampler,
+    ) -> Option<Ray>;
+
+    /// Light emitted at the hit point. Most materials do not glow.
+    fn emitted(&self, _rec: &HitRecord) -> Colour {
+        BLACK
+    }
 }
 
 pub struct Lambertian {
-    pub albedo: Colour,
+    pub albedo: Arc<dyn Texture>,
 }
 
 impl Lambertian {
     pub fn new(a: &Colour) -> Self {
-        Self { albedo: *a }
+        Self::with_texture(Arc::new(SolidColour::new(a)))
+    }
+
+    pub fn with_texture(albedo: Arc<dyn Texture>) -> Self {
+        Self { albedo }
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _ray: &Ray, rec: &HitRecord, attenuation: &mut Colour) -> Option<Ray> {
-        let mut scatter_dir = rec.n + Vec3::random_unit_vec();
+    fn scatter(
+        &self,
+        ray: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Colour,
+        sampler: &mut Sampler,
+    ) -> Option<Ray> {
+        let mut scatter_dir = rec.n + sampler::sphere(sampler.get_2d());
         if scatter_dir.near_zero() {
             scatter_dir = rec.n;
         }
 
-        *attenuation = self.albedo;
-        Some(Ray::new(&rec.p, &scatter_dir))
+        *attenuation = self.albedo.value(rec.u, rec.v, &rec.p);
+        Some(Ray::new(&rec.p, &scatter_dir).with_time(ray.time()))
     }
 }
 
 pub struct Metal {
-    pub albedo: Colour,
+    pub albedo: Arc<dyn Texture>,
     pub fuzz: f64,
 }
 
 impl Metal {
     pub fn new(a: &Colour, f: f64) -> Self {
+        Self::with_texture(Arc::new(SolidColour::new(a)), f)
+    }
+
+    pub fn with_texture(albedo: Arc<dyn Texture>, f: f64) -> Self {
         Self {
-            albedo: *a,
+            albedo,
             fuzz: if f < 1. { f } else { 1. },
         }
     }
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray: &Ray, rec: &HitRecord, attenuation: &mut Colour) -> Option<Ray> {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Colour,
+        sampler: &mut Sampler,
+    ) -> Option<Ray> {
         let reflected = ray.dir().unit_vector().reflect(&rec.n);
         let scattered = Ray::new(
             &rec.p,
-            &(reflected + self.fuzz * Vec3::random_in_unit_sphere()),
-        );
-        *attenuation = self.albedo;
+            &(reflected + self.fuzz * sampler::ball(sampler.get_2d(), sampler.get_1d())),
+        )
+        .with_time(ray.time());
+        *attenuation = self.albedo.value(rec.u, rec.v, &rec.p);
 
         if scattered.dir().dot(&rec.n) < 0. {
             return None;
@@ -80,7 +116,13 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray: &Ray, rec: &HitRecord, attenuation: &mut Colour) -> Option<Ray> {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        rec: &HitRecord,
+        attenuation: &mut Colour,
+        sampler: &mut Sampler,
+    ) -> Option<Ray> {
         *attenuation = WHITE;
         let refraction_ratio = if rec.front_face {
             1. / self.refraction_idx
@@ -93,7 +135,7 @@ impl Material for Dielectric {
         let cos_theta = (-unit_dir).dot(&rec.n).min(1.);
         let sin_theta = (1. - cos_theta.powi(2)).sqrt();
 
-        let rand = rand::thread_rng().gen_range(0.0..1.);
+        let rand = sampler.get_1d();
         let cannot_refract = (refraction_ratio * sin_theta > 1.)
             || (Dielectric::reflectance(cos_theta, refraction_ratio) > rand);
 
@@ -103,6 +145,37 @@ impl Material for Dielectric {
             unit_dir.reflect(&rec.n)
         };
 
-        Some(Ray::new(&rec.p, &direction))
+        Some(Ray::new(&rec.p, &direction).with_time(ray.time()))
+    }
+}
+
+/// Emits light and absorbs everything that hits it.
+pub struct DiffuseLight {
+    pub emit: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: &Colour) -> Self {
+        Self::with_texture(Arc::new(SolidColour::new(emit)))
+    }
+
+    pub fn with_texture(emit: Arc<dyn Texture>) -> Self {
+        Self { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(
+        &self,
+        _ray: &Ray,
+        _rec: &HitRecord,
+        _attenuation: &mut Colour,
+        _sampler: &mut Sampler,
+    ) -> Option<Ray> {
+        None
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Colour {
+        self.emit.value(rec.u, rec.v, &rec.p)
     }
 }
@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    bvh::Bvh,
+    hittable::{HitRecord, Hittable, HittableList},
+    material::Material,
+    ray::Ray,
+    triangle::{self, Uv},
+    vec3::{Point, Vec3},
+};
+
+/// Vertex attribute buffers shared by every triangle of one or more meshes.
+#[derive(Default)]
+pub struct MeshBuffers {
+    pub positions: Vec<Point>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<Uv>,
+}
+
+/// Indices of a triangle's corners into [`MeshBuffers`]. Normals and UVs are
+/// indexed separately from positions, as in OBJ files.
+#[derive(Copy, Clone, Debug)]
+pub struct Face {
+    pub v: [usize; 3],
+    pub n: Option<[usize; 3]>,
+    pub uv: Option<[usize; 3]>,
+}
+
+impl Face {
+    pub fn new(v: [usize; 3]) -> Self {
+        Self {
+            v,
+            n: None,
+            uv: None,
+        }
+    }
+
+    fn is_valid(&self, buffers: &MeshBuffers) -> bool {
+        self.v.iter().all(|&i| i < buffers.positions.len())
+            && self
+                .n
+                .is_none_or(|n| n.iter().all(|&i| i < buffers.normals.len()))
+            && self
+                .uv
+                .is_none_or(|uv| uv.iter().all(|&i| i < buffers.uvs.len()))
+    }
+}
+
+struct MeshTriangle {
+    buffers: Arc<MeshBuffers>,
+    face: Face,
+    mat: Arc<dyn Material>,
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let pos = &self.buffers.positions;
+        let [i0, i1, i2] = self.face.v;
+        let (t, bary) = triangle::intersect(&pos[i0], &pos[i1], &pos[i2], ray, t_min, t_max)?;
+
+        let n = self.face.n.map(|[n0, n1, n2]| {
+            let normals = &self.buffers.normals;
+            [&normals[n0], &normals[n1], &normals[n2]]
+        });
+        let uv = self.face.uv.map(|[t0, t1, t2]| {
+            let uvs = &self.buffers.uvs;
+            [uvs[t0], uvs[t1], uvs[t2]]
+        });
+
+        Some(triangle::shade(
+            ray,
+            t,
+            bary,
+            [&pos[i0], &pos[i1], &pos[i2]],
+            n,
+            uv,
+            &self.mat,
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let pos = &self.buffers.positions;
+        let [i0, i1, i2] = self.face.v;
+        Some(triangle::bounds(&pos[i0], &pos[i1], &pos[i2]))
+    }
+}
+
+/// Indexed triangle mesh with its own BVH over its faces.
+pub struct TriangleMesh {
+    bvh: Bvh,
+}
+
+impl TriangleMesh {
+    /// Panics if a face indexes outside `buffers`.
+    pub fn new(buffers: Arc<MeshBuffers>, faces: &[Face], mat: Arc<dyn Material>) -> Self {
+        let mut triangles = HittableList::new();
+
+        for face in faces {
+            assert!(face.is_valid(&buffers), "face {face:?} out of bounds");
+            triangles.add(MeshTriangle {
+                buffers: buffers.clone(),
+                face: *face,
+                mat: mat.clone(),
+            });
+        }
+
+        Self {
+            bvh: Bvh::new(triangles),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bvh.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bvh.is_empty()
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.bvh.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bvh.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::Lambertian, vec3::Colour};
+
+    fn quad() -> (Arc<MeshBuffers>, Vec<Face>) {
+        let buffers = MeshBuffers {
+            positions: vec![
+                Point::new(0., 0., 0.),
+                Point::new(1., 0., 0.),
+                Point::new(1., 1., 0.),
+                Point::new(0., 1., 0.),
+            ],
+            normals: vec![Vec3::new(0., 0., 1.), Vec3::new(1., 0., 1.).unit_vector()],
+            uvs: vec![(0., 0.), (1., 0.), (1., 1.), (0., 1.)],
+        };
+        let faces = vec![
+            Face {
+                v: [0, 1, 2],
+                n: Some([0, 1, 1]),
+                uv: Some([0, 1, 2]),
+            },
+            Face {
+                v: [0, 2, 3],
+                n: Some([0, 1, 0]),
+                uv: Some([0, 2, 3]),
+            },
+        ];
+
+        (Arc::new(buffers), faces)
+    }
+
+    #[test]
+    fn shares_buffers() {
+        let (buffers, faces) = quad();
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(&Colour::default()));
+        let mesh = TriangleMesh::new(buffers.clone(), &faces, mat);
+
+        assert_eq!(mesh.len(), 2);
+        assert_eq!(Arc::strong_count(&buffers), 3);
+    }
+
+    #[test]
+    fn hit_interpolates_attributes() {
+        let (buffers, faces) = quad();
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(&Colour::default()));
+        let mesh = TriangleMesh::new(buffers, &faces, mat);
+
+        let ray = Ray::new(&Point::new(0.2, 0.7, 1.), &Vec3::new(0., 0., -1.));
+        let rec = mesh.hit(&ray, 0.001, f64::INFINITY).unwrap();
+
+        assert!((rec.t - 1.).abs() < 1e-12);
+        assert!((rec.u - 0.2).abs() < 1e-12);
+        assert!((rec.v - 0.7).abs() < 1e-12);
+        assert!(rec.n.x > 0. && rec.n.z > 0.);
+        assert!((rec.n.len() - 1.).abs() < 1e-12);
+
+        let miss = Ray::new(&Point::new(1.2, 0.7, 1.), &Vec3::new(0., 0., -1.));
+        assert!(mesh.hit(&miss, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_bad_indices() {
+        let (buffers, _) = quad();
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(&Colour::default()));
+        TriangleMesh::new(buffers, &[Face::new([0, 1, 4])], mat);
+    }
+}
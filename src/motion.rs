@@ -0,0 +1,193 @@
+//! Values that change over time, for motion blur and animation.
+
+use serde::Deserialize;
+
+use crate::vec3::Vec3;
+
+/// Straight-line blend between two values, `t` running from 0 to 1. Values of
+/// `t` outside that range extrapolate.
+pub trait Lerp: Copy {
+    fn lerp(&self, other: &Self, t: f64) -> Self;
+}
+
+impl Lerp for f64 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        (1. - t) * self + t * other
+    }
+}
+
+impl Lerp for Vec3 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        (1. - t) * *self + t * *other
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    /// Straight lines between keys.
+    #[default]
+    Linear,
+    /// Smooth cubic Bezier curve through the keys, with handles placed
+    /// automatically from the neighbouring keys (a Catmull-Rom spline).
+    Bezier,
+}
+
+/// A value keyed at moments in time and interpolated in between. Before the
+/// first key and after the last one the value holds still.
+#[derive(Clone, Debug)]
+pub struct Keyframes<T> {
+    keys: Vec<(f64, T)>,
+    interpolation: Interpolation,
+}
+
+impl<T: Lerp> Keyframes<T> {
+    /// Panics without any keys.
+    pub fn new(mut keys: Vec<(f64, T)>) -> Self {
+        assert!(!keys.is_empty(), "no keyframes");
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self {
+            keys,
+            interpolation: Interpolation::Linear,
+        }
+    }
+
+    pub fn constant(value: T) -> Self {
+        Self::new(vec![(0., value)])
+    }
+
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    pub fn at(&self, time: f64) -> T {
+        let i = self.keys.partition_point(|(t, _)| *t <= time);
+        if i == 0 {
+            return self.keys[0].1;
+        }
+        if i == self.keys.len() {
+            return self.keys[i - 1].1;
+        }
+
+        let (t1, p1) = self.keys[i - 1];
+        let (t2, p2) = self.keys[i];
+        match self.interpolation {
+            Interpolation::Linear => p1.lerp(&p2, (time - t1) / (t2 - t1)),
+            Interpolation::Bezier => {
+                // Past the ends, mirror the neighbouring key's timing and
+                // repeat the end value so the curve eases in and out.
+                let (t0, p0) = match i {
+                    1 => (2. * t1 - t2, p1),
+                    _ => self.keys[i - 2],
+                };
+                let (t3, p3) = match self.keys.get(i + 1) {
+                    Some(&key) => key,
+                    None => (2. * t2 - t1, p2),
+                };
+                catmull_rom([(t0, p0), (t1, p1), (t2, p2), (t3, p3)], time)
+            }
+        }
+    }
+
+    /// Values whose convex hull contains every value the keyframes take,
+    /// for bounding moving objects.
+    pub fn hull(&self) -> Vec<T> {
+        let mut points: Vec<T> = self.keys.iter().map(|(_, v)| *v).collect();
+        if self.interpolation == Interpolation::Bezier {
+            for pair in self.keys.windows(2) {
+                let (t1, t2) = (pair[0].0, pair[1].0);
+                let at = |s: f64| self.at(t1 + s * (t2 - t1));
+                let (a, b, c, d) = (at(0.), at(1. / 3.), at(2. / 3.), at(1.));
+
+                // The segment is a cubic whose Bezier handles are
+                // (-5a + 18b - 9c + 2d) / 6 and (2a - 9b + 18c - 5d) / 6.
+                let handle = |a: T, b: T, c: T, d: T| {
+                    a.lerp(&b, 18. / 13.).lerp(&c.lerp(&d, -2. / 7.), -7. / 6.)
+                };
+                points.push(handle(a, b, c, d));
+                points.push(handle(d, c, b, a));
+            }
+        }
+        points
+    }
+
+    pub fn keys(&self) -> &[(f64, T)] {
+        &self.keys
+    }
+}
+
+/// Barry-Goldman evaluation of a Catmull-Rom segment between the middle two
+/// keys, which only needs (extrapolating) lerps.
+fn catmull_rom<T: Lerp>(keys: [(f64, T); 4], time: f64) -> T {
+    let [(t0, p0), (t1, p1), (t2, p2), (t3, p3)] = keys;
+    let blend = |a: &T, b: &T, ta: f64, tb: f64| a.lerp(b, (time - ta) / (tb - ta));
+
+    let a1 = blend(&p0, &p1, t0, t1);
+    let a2 = blend(&p1, &p2, t1, t2);
+    let a3 = blend(&p2, &p3, t2, t3);
+    let b1 = blend(&a1, &a2, t0, t2);
+    let b2 = blend(&a2, &a3, t1, t3);
+    blend(&b1, &b2, t1, t2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolates_between_keys() {
+        let k = Keyframes::new(vec![(1., 10.), (0., 0.), (3., 0.)]);
+
+        assert_eq!(k.at(-1.), 0.);
+        assert_eq!(k.at(0.5), 5.);
+        assert_eq!(k.at(1.), 10.);
+        assert_eq!(k.at(2.), 5.);
+        assert_eq!(k.at(7.), 0.);
+    }
+
+    #[test]
+    fn bezier_passes_through_keys_smoothly() {
+        let k = Keyframes::new(vec![(0., 0.), (1., 1.), (2., 0.), (4., 2.)])
+            .with_interpolation(Interpolation::Bezier);
+
+        for (t, v) in [(0., 0.), (1., 1.), (2., 0.), (4., 2.)] {
+            assert!((k.at(t) - v).abs() < 1e-12);
+        }
+
+        // Overshoots the linear path near the peak instead of turning sharply.
+        assert!(k.at(0.9) > 0.9 && k.at(1.1) > 0.9);
+
+        // The slope is continuous across a key.
+        let slope = |t: f64| (k.at(t + 1e-6) - k.at(t - 1e-6)) / 2e-6;
+        assert!((slope(2. - 1e-4) - slope(2. + 1e-4)).abs() < 1e-2);
+
+        // Evenly spaced keys on a line stay on it.
+        let line = Keyframes::new(vec![(0., 0.), (1., 1.), (2., 2.), (3., 3.)])
+            .with_interpolation(Interpolation::Bezier);
+        assert!((line.at(1.5) - 1.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn hull_covers_path() {
+        let keys = vec![(0., 0.), (1., 1.), (1.5, -1.), (4., 2.)];
+        for interpolation in [Interpolation::Linear, Interpolation::Bezier] {
+            let k = Keyframes::new(keys.clone()).with_interpolation(interpolation);
+            let hull = k.hull();
+            let lo = hull.iter().cloned().fold(f64::INFINITY, f64::min);
+            let hi = hull.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+            for i in 0..=400 {
+                let v = k.at(i as f64 / 100.);
+                assert!(v >= lo - 1e-12 && v <= hi + 1e-12, "{interpolation:?}: {v}");
+            }
+        }
+    }
+
+    #[test]
+    fn constant() {
+        let k = Keyframes::constant(Vec3::new(1., 2., 3.));
+        let v = k.at(42.);
+        assert_eq!((v.x, v.y, v.z), (1., 2., 3.));
+    }
+}
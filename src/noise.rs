@@ -0,0 +1,223 @@
+//! Seedable 3D noise functions for procedural textures.
+
+use crate::{
+    rng::Rng,
+    vec3::{Point, Vec3},
+};
+
+const POINT_COUNT: usize = 256;
+
+/// Gradient noise over a lattice of random unit vectors.
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        // The crate's own generator, so a seed gives the same texture whatever
+        // version of `rand` is linked.
+        let mut rng = Rng::new(seed, 0);
+        let gradients = (0..POINT_COUNT)
+            .map(|_| {
+                let v = Vec3::new(
+                    rng.random_range(-1., 1.),
+                    rng.random_range(-1., 1.),
+                    rng.random_range(-1., 1.),
+                );
+                if v.near_zero() {
+                    Vec3::new(1., 0., 0.)
+                } else {
+                    v.unit_vector()
+                }
+            })
+            .collect();
+
+        let mut permutation = || {
+            // Fisher-Yates.
+            let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+            for i in (1..POINT_COUNT).rev() {
+                p.swap(i, (rng.random() * (i + 1) as f64) as usize);
+            }
+            p
+        };
+        let perm_x = permutation();
+        let perm_y = permutation();
+        let perm_z = permutation();
+
+        Self {
+            gradients,
+            perm_x,
+            perm_y,
+            perm_z,
+        }
+    }
+
+    /// Noise value at `p`, roughly in `[-1, 1]` and zero on lattice points.
+    pub fn noise(&self, p: &Point) -> f64 {
+        let (fx, fy, fz) = (p.x.floor(), p.y.floor(), p.z.floor());
+        let (u, v, w) = (p.x - fx, p.y - fy, p.z - fz);
+        let (i, j, k) = (fx as i64, fy as i64, fz as i64);
+
+        // Hermite smoothing removes the grid artefacts of plain trilinear blending.
+        let (uu, vv, ww) = (smooth(u), smooth(v), smooth(w));
+        let wrap = |n: i64| n.rem_euclid(POINT_COUNT as i64) as usize;
+
+        let mut acc = 0.;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let hash = self.perm_x[wrap(i + di)]
+                        ^ self.perm_y[wrap(j + dj)]
+                        ^ self.perm_z[wrap(k + dk)];
+                    let weight = Vec3::new(u - di as f64, v - dj as f64, w - dk as f64);
+                    let (a, b, c) = (di as f64, dj as f64, dk as f64);
+
+                    acc += (a * uu + (1. - a) * (1. - uu))
+                        * (b * vv + (1. - b) * (1. - vv))
+                        * (c * ww + (1. - c) * (1. - ww))
+                        * self.gradients[hash].dot(&weight);
+                }
+            }
+        }
+
+        acc
+    }
+
+    /// Fractal sum of `octaves` layers of `|noise|`, each at twice the
+    /// frequency and half the amplitude of the previous one. Lies in `[0, 2)`.
+    pub fn turbulence(&self, p: &Point, octaves: u32) -> f64 {
+        let mut acc = 0.;
+        let mut p = *p;
+        let mut weight = 1.;
+
+        for _ in 0..octaves {
+            acc += weight * self.noise(&p).abs();
+            weight *= 0.5;
+            p *= 2.;
+        }
+
+        acc
+    }
+}
+
+fn smooth(t: f64) -> f64 {
+    t * t * (3. - 2. * t)
+}
+
+/// Cellular noise: distance to the nearest of one random feature point per
+/// unit cell.
+pub struct Worley {
+    seed: u64,
+}
+
+impl Worley {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Distance from `p` to the closest feature point, in `[0, sqrt(3)]`.
+    pub fn distance(&self, p: &Point) -> f64 {
+        let cell = [p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64];
+        let mut nearest = f64::INFINITY;
+
+        for di in -1..=1 {
+            for dj in -1..=1 {
+                for dk in -1..=1 {
+                    let c = [cell[0] + di, cell[1] + dj, cell[2] + dk];
+                    let d = (self.feature_point(c) - *p).len_squared();
+                    nearest = nearest.min(d);
+                }
+            }
+        }
+
+        nearest.sqrt()
+    }
+
+    fn feature_point(&self, cell: [i64; 3]) -> Point {
+        let mut h = self.seed;
+        for c in cell {
+            h = splitmix64(h ^ c as u64);
+        }
+        let unit = |h: u64| (h >> 11) as f64 / (1u64 << 53) as f64;
+
+        Point::new(
+            cell[0] as f64 + unit(h),
+            cell[1] as f64 + unit(splitmix64(h)),
+            cell[2] as f64 + unit(splitmix64(h ^ 0x5851_f42d_4c95_7f2d)),
+        )
+    }
+}
+
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points() -> impl Iterator<Item = Point> {
+        (0..1000).map(|i| {
+            let i = i as f64;
+            Point::new(i * 0.137 - 50., i * 0.071, (i * 0.53).sin() * 20.)
+        })
+    }
+
+    #[test]
+    fn perlin_is_seeded() {
+        let (a, b, c) = (Perlin::new(1), Perlin::new(1), Perlin::new(2));
+        let p = Point::new(0.3, 1.7, -2.2);
+
+        assert_eq!(a.noise(&p), b.noise(&p));
+        assert_ne!(a.noise(&p), c.noise(&p));
+        // Pinned, so the same seed keeps giving the same texture.
+        assert!((a.noise(&p) - 0.110791124792077).abs() < 1e-12);
+    }
+
+    #[test]
+    fn perlin_range() {
+        let perlin = Perlin::new(7);
+        for p in points() {
+            let n = perlin.noise(&p);
+            assert!((-1. ..=1.).contains(&n), "{n} at {p}");
+
+            let t = perlin.turbulence(&p, 7);
+            assert!((0. ..2.).contains(&t), "{t} at {p}");
+        }
+
+        // Gradient noise vanishes on the integer lattice.
+        assert_eq!(perlin.noise(&Point::new(3., -4., 5.)), 0.);
+    }
+
+    #[test]
+    fn perlin_is_continuous() {
+        let perlin = Perlin::new(3);
+        for p in points() {
+            let q = p + Vec3::new(1e-6, 1e-6, 1e-6);
+            assert!((perlin.noise(&p) - perlin.noise(&q)).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn worley() {
+        let (a, b) = (Worley::new(1), Worley::new(2));
+        let p = Point::new(0.3, 1.7, -2.2);
+        assert_eq!(a.distance(&p), Worley::new(1).distance(&p));
+        assert_ne!(a.distance(&p), b.distance(&p));
+
+        for p in points() {
+            let d = a.distance(&p);
+            assert!((0. ..=3f64.sqrt()).contains(&d), "{d} at {p}");
+        }
+
+        // Zero at a feature point.
+        let f = a.feature_point([2, -1, 0]);
+        assert!(a.distance(&f) < 1e-12);
+    }
+}
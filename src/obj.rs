@@ -0,0 +1,611 @@
+//! Wavefront OBJ/MTL importer.
+//!
+//! Every `usemtl` group becomes a [`TriangleMesh`]; all groups of a file share
+//! the same vertex buffers. Polygons are triangulated as fans.
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    colour::ColourSpace,
+    hittable::HittableList,
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    mesh::{Face, MeshBuffers, TriangleMesh},
+    texture::{ImageTexture, TextureError},
+    vec3::{Colour, Point, Vec3},
+};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        file: String,
+        line: usize,
+        msg: String,
+    },
+    Unsupported {
+        file: String,
+        line: usize,
+        statement: String,
+    },
+    Texture(TextureError),
+}
+
+impl Display for ObjError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ObjError::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            ObjError::Parse { file, line, msg } => write!(f, "{file}:{line}: {msg}"),
+            ObjError::Unsupported {
+                file,
+                line,
+                statement,
+            } => write!(f, "{file}:{line}: unsupported statement `{statement}`"),
+            ObjError::Texture(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for ObjError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ObjError::Io { source, .. } => Some(source),
+            ObjError::Texture(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Material parameters read from an MTL file.
+#[derive(Clone, Debug)]
+pub struct MtlMaterial {
+    pub kd: Colour,
+    pub ks: Colour,
+    pub ke: Colour,
+    pub ns: f64,
+    pub ni: f64,
+    pub dissolve: f64,
+    pub illum: u32,
+    /// Diffuse colour texture (`map_Kd`).
+    pub map_kd: Option<PathBuf>,
+    /// Working space to convert the (sRGB) colours to.
+    pub colour_space: ColourSpace,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        Self {
+            kd: Colour::new(0.8, 0.8, 0.8),
+            ks: Colour::default(),
+            ke: Colour::default(),
+            ns: 0.,
+            ni: 1.5,
+            dissolve: 1.,
+            illum: 2,
+            map_kd: None,
+            colour_space: ColourSpace::default(),
+        }
+    }
+}
+
+impl MtlMaterial {
+    /// Maps the MTL parameters onto the closest material of the crate:
+    /// emissive materials become [`DiffuseLight`], transparent materials
+    /// become [`Dielectric`], materials whose specular
+    /// reflectance dominates (or with `illum 3`) become [`Metal`] with a
+    /// fuzz derived from the `Ns` exponent, everything else is [`Lambertian`].
+    /// A `map_Kd` texture replaces the diffuse colour of Lambertian materials.
+    pub fn to_material(&self) -> Result<Arc<dyn Material>, TextureError> {
+        let max = |c: &Colour| c.x.max(c.y).max(c.z);
+        let colour = |c: &Colour| self.colour_space.from_srgb(c);
+
+        if max(&self.ke) > 0. {
+            return Ok(Arc::new(DiffuseLight::new(&colour(&self.ke))));
+        }
+
+        if self.dissolve < 1. || matches!(self.illum, 4 | 6 | 7 | 9) {
+            return Ok(Arc::new(Dielectric::new(self.ni)));
+        }
+
+        if self.illum == 3 || max(&self.ks) > max(&self.kd) {
+            let fuzz = 1. - (self.ns / 1000.).clamp(0., 1.).sqrt();
+            return Ok(Arc::new(Metal::new(&colour(&self.ks), fuzz)));
+        }
+
+        match &self.map_kd {
+            Some(path) => Ok(Arc::new(Lambertian::with_texture(
+                self.colour_space
+                    .texture(Arc::new(ImageTexture::load(path)?)),
+            ))),
+            None => Ok(Arc::new(Lambertian::new(&colour(&self.kd)))),
+        }
+    }
+}
+
+// Position, texture and normal indices of one face corner.
+type Corner = (usize, Option<usize>, Option<usize>);
+
+struct Parser<'a> {
+    file: &'a str,
+    line: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, msg: impl Into<String>) -> ObjError {
+        ObjError::Parse {
+            file: self.file.to_string(),
+            line: self.line,
+            msg: msg.into(),
+        }
+    }
+
+    fn unsupported(&self, statement: &str) -> ObjError {
+        ObjError::Unsupported {
+            file: self.file.to_string(),
+            line: self.line,
+            statement: statement.to_string(),
+        }
+    }
+
+    fn floats<const N: usize>(&self, args: &[&str], keyword: &str) -> Result<[f64; N], ObjError> {
+        if args.len() < N {
+            return Err(self.error(format!("`{keyword}` expects {N} numbers")));
+        }
+
+        let mut out = [0.; N];
+        for (o, a) in out.iter_mut().zip(args) {
+            *o = a
+                .parse()
+                .map_err(|_| self.error(format!("invalid number `{a}` in `{keyword}`")))?;
+        }
+        Ok(out)
+    }
+
+    fn float(&self, args: &[&str], keyword: &str) -> Result<f64, ObjError> {
+        Ok(self.floats::<1>(args, keyword)?[0])
+    }
+
+    fn colour(&self, args: &[&str], keyword: &str) -> Result<Colour, ObjError> {
+        if args.first() == Some(&"spectral") || args.first() == Some(&"xyz") {
+            return Err(self.unsupported(&format!("{keyword} {}", args[0])));
+        }
+        // A single value is a grey.
+        if args.len() == 1 {
+            let g = self.float(args, keyword)?;
+            return Ok(Colour::new(g, g, g));
+        }
+        let [r, g, b] = self.floats(args, keyword)?;
+        Ok(Colour::new(r, g, b))
+    }
+
+    fn index(&self, s: &str, count: usize, kind: &str) -> Result<usize, ObjError> {
+        let i: i64 = s
+            .parse()
+            .map_err(|_| self.error(format!("invalid {kind} index `{s}`")))?;
+
+        // Negative indices are relative to the end of the list so far.
+        let resolved = match i {
+            0 => None,
+            i if i > 0 => Some(i as usize - 1),
+            i => count.checked_sub(i.unsigned_abs() as usize),
+        };
+
+        match resolved {
+            Some(r) if r < count => Ok(r),
+            _ => Err(self.error(format!("{kind} index {i} out of range ({count} defined)"))),
+        }
+    }
+}
+
+fn statements(src: &str) -> impl Iterator<Item = (usize, &str, Vec<&str>)> {
+    src.lines().enumerate().filter_map(|(i, line)| {
+        let line = line.split('#').next().unwrap_or("");
+        let mut words = line.split_whitespace();
+        let keyword = words.next()?;
+        Some((i + 1, keyword, words.collect()))
+    })
+}
+
+/// Parses the contents of an MTL file.
+pub fn parse_mtl(src: &str, file: &str) -> Result<HashMap<String, MtlMaterial>, ObjError> {
+    let mut p = Parser { file, line: 0 };
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+
+    for (line, keyword, args) in statements(src) {
+        p.line = line;
+
+        if keyword == "newmtl" {
+            let name = args.join(" ");
+            if name.is_empty() {
+                return Err(p.error("`newmtl` without a name"));
+            }
+            if let Some((name, mat)) = current.take() {
+                materials.insert(name, mat);
+            }
+            current = Some((name, MtlMaterial::default()));
+            continue;
+        }
+
+        let mat = match current.as_mut() {
+            Some((_, mat)) => mat,
+            None => return Err(p.error(format!("`{keyword}` before any `newmtl`"))),
+        };
+
+        match keyword {
+            "Kd" => mat.kd = p.colour(&args, keyword)?,
+            "Ks" => mat.ks = p.colour(&args, keyword)?,
+            "Ke" => mat.ke = p.colour(&args, keyword)?,
+            "Ns" => mat.ns = p.float(&args, keyword)?,
+            "Ni" => mat.ni = p.float(&args, keyword)?,
+            "d" => mat.dissolve = p.float(&args, keyword)?,
+            "Tr" => mat.dissolve = 1. - p.float(&args, keyword)?,
+            "illum" => {
+                mat.illum = args
+                    .first()
+                    .and_then(|a| a.parse().ok())
+                    .ok_or_else(|| p.error("`illum` expects an integer"))?
+            }
+            "map_Kd" => match args.as_slice() {
+                [file] => mat.map_kd = Some(PathBuf::from(file)),
+                [] => return Err(p.error("`map_Kd` without a file name")),
+                // Texture options such as -s or -clamp.
+                _ => return Err(p.unsupported(&args[..args.len() - 1].join(" "))),
+            },
+            // Ambient and transmission filter have no counterpart in a path tracer.
+            "Ka" | "Tf" => {}
+            _ => return Err(p.unsupported(keyword)),
+        }
+    }
+
+    if let Some((name, mat)) = current {
+        materials.insert(name, mat);
+    }
+
+    Ok(materials)
+}
+
+/// Parses the contents of an OBJ file.
+///
+/// `load_mtl` is called with the argument of every `mtllib` statement and
+/// returns the parsed library.
+pub fn parse_obj(
+    src: &str,
+    file: &str,
+    mut load_mtl: impl FnMut(&str) -> Result<HashMap<String, MtlMaterial>, ObjError>,
+) -> Result<HittableList, ObjError> {
+    let mut p = Parser { file, line: 0 };
+    let mut buffers = MeshBuffers::default();
+    let mut library: HashMap<String, MtlMaterial> = HashMap::new();
+
+    // Faces grouped by material name, in order of first use.
+    let mut groups: Vec<(Option<String>, Vec<Face>)> = vec![(None, Vec::new())];
+    let mut current = 0;
+
+    for (line, keyword, args) in statements(src) {
+        p.line = line;
+
+        match keyword {
+            "v" => {
+                let [x, y, z] = p.floats(&args, keyword)?;
+                buffers.positions.push(Point::new(x, y, z));
+            }
+            "vn" => {
+                let [x, y, z] = p.floats(&args, keyword)?;
+                buffers.normals.push(Vec3::new(x, y, z));
+            }
+            "vt" => {
+                // The optional third (w) coordinate is ignored; v defaults to 0.
+                let u = p.float(&args, keyword)?;
+                let v = match args.get(1) {
+                    Some(_) => p.floats::<2>(&args, keyword)?[1],
+                    None => 0.,
+                };
+                buffers.uvs.push((u, v));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(p.error("face with fewer than 3 vertices"));
+                }
+
+                let mut corners: Vec<Corner> = Vec::with_capacity(args.len());
+                for arg in &args {
+                    let mut parts = arg.split('/');
+                    let v = p.index(
+                        parts.next().unwrap_or(""),
+                        buffers.positions.len(),
+                        "vertex",
+                    )?;
+                    let uv = match parts.next() {
+                        Some("") | None => None,
+                        Some(t) => Some(p.index(t, buffers.uvs.len(), "texture")?),
+                    };
+                    let n = match parts.next() {
+                        Some("") | None => None,
+                        Some(n) => Some(p.index(n, buffers.normals.len(), "normal")?),
+                    };
+                    if parts.next().is_some() {
+                        return Err(p.error(format!("malformed face vertex `{arg}`")));
+                    }
+                    corners.push((v, uv, n));
+                }
+
+                for i in 1..corners.len() - 1 {
+                    let tri = [corners[0], corners[i], corners[i + 1]];
+                    let all = |f: fn(&Corner) -> Option<usize>| {
+                        Some([f(&tri[0])?, f(&tri[1])?, f(&tri[2])?])
+                    };
+                    groups[current].1.push(Face {
+                        v: [tri[0].0, tri[1].0, tri[2].0],
+                        uv: all(|c| c.1),
+                        n: all(|c| c.2),
+                    });
+                }
+            }
+            "usemtl" => {
+                let name = args.join(" ");
+                if !library.contains_key(&name) {
+                    return Err(p.error(format!("unknown material `{name}`")));
+                }
+                current = match groups.iter().position(|(n, _)| n.as_ref() == Some(&name)) {
+                    Some(i) => i,
+                    None => {
+                        groups.push((Some(name), Vec::new()));
+                        groups.len() - 1
+                    }
+                };
+            }
+            "mtllib" => {
+                if args.is_empty() {
+                    return Err(p.error("`mtllib` without a file name"));
+                }
+                for name in &args {
+                    library.extend(load_mtl(name)?);
+                }
+            }
+            // Grouping and smoothing groups do not affect rendering.
+            "o" | "g" | "s" => {}
+            _ => return Err(p.unsupported(keyword)),
+        }
+    }
+
+    let buffers = Arc::new(buffers);
+    let mut world = HittableList::new();
+
+    for (name, faces) in groups {
+        if faces.is_empty() {
+            continue;
+        }
+        let mat = match name {
+            Some(name) => library[&name].to_material(),
+            None => MtlMaterial::default().to_material(),
+        }
+        .map_err(ObjError::Texture)?;
+        world.add(TriangleMesh::new(buffers.clone(), &faces, mat));
+    }
+
+    Ok(world)
+}
+
+fn read(path: &Path) -> Result<String, ObjError> {
+    fs::read_to_string(path).map_err(|source| ObjError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Loads an OBJ file, resolving `mtllib` statements relative to its directory.
+pub fn load_obj(path: impl AsRef<Path>) -> Result<HittableList, ObjError> {
+    load_obj_in(path, ColourSpace::Srgb)
+}
+
+/// Loads an OBJ file for rendering in the working space `colour_space`.
+pub fn load_obj_in(
+    path: impl AsRef<Path>,
+    colour_space: ColourSpace,
+) -> Result<HittableList, ObjError> {
+    let path = path.as_ref();
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let src = read(path)?;
+
+    parse_obj(&src, &path.display().to_string(), |name| {
+        let mtl_path = dir.join(name);
+        let mut library = parse_mtl(&read(&mtl_path)?, &mtl_path.display().to_string())?;
+
+        // Texture maps are relative to the MTL file.
+        let mtl_dir = mtl_path.parent().unwrap_or(Path::new(""));
+        for mat in library.values_mut() {
+            mat.colour_space = colour_space;
+            if let Some(map) = &mat.map_kd {
+                mat.map_kd = Some(mtl_dir.join(map));
+            }
+        }
+
+        Ok(library)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hittable::Hittable, ray::Ray};
+
+    const MTL: &str = "
+# materials
+newmtl red
+Kd 0.8 0.1 0.1
+Ka 0 0 0
+
+newmtl mirror
+Kd 0.1 0.1 0.1
+Ks 0.9 0.9 0.9
+Ns 1000
+
+newmtl glass
+Ni 1.33
+d 0.2
+";
+
+    fn no_mtl(_: &str) -> Result<HashMap<String, MtlMaterial>, ObjError> {
+        Ok(HashMap::new())
+    }
+
+    fn with_mtl(_: &str) -> Result<HashMap<String, MtlMaterial>, ObjError> {
+        parse_mtl(MTL, "test.mtl")
+    }
+
+    fn down(x: f64, y: f64) -> Ray {
+        Ray::new(&Point::new(x, y, 1.), &Vec3::new(0., 0., -1.))
+    }
+
+    #[test]
+    fn mtl_parameters() {
+        let lib = parse_mtl(MTL, "test.mtl").unwrap();
+
+        assert_eq!(lib.len(), 3);
+        assert_eq!(lib["red"].kd.x, 0.8);
+        assert_eq!(lib["mirror"].ks.y, 0.9);
+        assert_eq!(lib["mirror"].ns, 1000.);
+        assert_eq!(lib["glass"].ni, 1.33);
+        assert_eq!(lib["glass"].dissolve, 0.2);
+    }
+
+    #[test]
+    fn quad_is_triangulated() {
+        let src = "
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+f 1/1 2/2 3/3 4/4
+";
+        let world = parse_obj(src, "quad.obj", no_mtl).unwrap();
+        assert_eq!(world.objects.len(), 1);
+
+        let rec = world.hit(&down(0.25, 0.75), 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 1.).abs() < 1e-12);
+        assert!((rec.u - 0.25).abs() < 1e-12);
+        assert!((rec.v - 0.75).abs() < 1e-12);
+
+        assert!(world.hit(&down(1.5, 0.5), 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn negative_indices_and_normals() {
+        let src = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vn 0 0 1
+f -3//-1 -2//-1 -1//-1
+";
+        let world = parse_obj(src, "tri.obj", no_mtl).unwrap();
+        let rec = world.hit(&down(0.2, 0.2), 0.001, f64::INFINITY).unwrap();
+        assert!((rec.n.z - 1.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn groups_by_material() {
+        let src = "
+mtllib scene.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 1 0
+usemtl red
+f 1 2 3
+usemtl glass
+f 2 4 3
+usemtl red
+f 1 3 2
+";
+        let world = parse_obj(src, "scene.obj", with_mtl).unwrap();
+        assert_eq!(world.objects.len(), 2);
+    }
+
+    #[test]
+    fn errors_report_line() {
+        let err = parse_obj("v 0 0 0\nv 1 0\n", "bad.obj", no_mtl)
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "bad.obj:2: `v` expects 3 numbers");
+
+        let err = parse_obj("v 0 0 0\nv 1 0 0\nf 1 2 3\n", "bad.obj", no_mtl)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "bad.obj:3: vertex index 3 out of range (2 defined)"
+        );
+
+        let err = parse_obj("v 0 0 0\nusemtl nope\n", "bad.obj", no_mtl)
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "bad.obj:2: unknown material `nope`");
+
+        let err = parse_mtl("Kd 1 1 1\n", "bad.mtl").unwrap_err();
+        assert_eq!(err.to_string(), "bad.mtl:1: `Kd` before any `newmtl`");
+    }
+
+    #[test]
+    fn unsupported_statements() {
+        let err = parse_obj("v 0 0 0\n\ncstype bspline\n", "curve.obj", no_mtl)
+            .err()
+            .unwrap();
+        assert!(matches!(err, ObjError::Unsupported { line: 3, .. }));
+        assert_eq!(
+            err.to_string(),
+            "curve.obj:3: unsupported statement `cstype`"
+        );
+
+        let err = parse_mtl("newmtl a\nbump normal.png\n", "a.mtl").unwrap_err();
+        assert!(matches!(err, ObjError::Unsupported { line: 2, .. }));
+
+        let err = parse_mtl("newmtl a\nmap_Kd -clamp on wood.png\n", "a.mtl").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "a.mtl:2: unsupported statement `-clamp on`"
+        );
+    }
+
+    #[test]
+    fn diffuse_texture_map() {
+        let dir = tempfile::tempdir().unwrap();
+        image::RgbImage::from_pixel(2, 2, image::Rgb([255, 0, 0]))
+            .save(dir.path().join("red.png"))
+            .unwrap();
+        fs::write(dir.path().join("tex.mtl"), "newmtl tex\nmap_Kd red.png\n").unwrap();
+        fs::write(
+            dir.path().join("tex.obj"),
+            "mtllib tex.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl tex\nf 1 2 3\n",
+        )
+        .unwrap();
+
+        let world = load_obj(dir.path().join("tex.obj")).unwrap();
+        assert_eq!(world.objects.len(), 1);
+
+        fs::write(dir.path().join("tex.mtl"), "newmtl tex\nmap_Kd blue.png\n").unwrap();
+        let err = load_obj(dir.path().join("tex.obj")).err().unwrap();
+        assert!(matches!(err, ObjError::Texture(TextureError::Io { .. })));
+    }
+
+    #[test]
+    fn missing_file() {
+        let err = load_obj("does/not/exist.obj").err().unwrap();
+        assert!(matches!(err, ObjError::Io { .. }));
+        assert!(err.to_string().starts_with("does/not/exist.obj: "));
+    }
+}
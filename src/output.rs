@@ -0,0 +1,494 @@
+//! Writing rendered images to disk.
+//!
+//! PNG and PPM images are expected in linear sRGB with displayable values in
+//! `[0, 1]` (after exposure and tone mapping); they are encoded with the sRGB
+//! transfer function and brighter values are clipped. OpenEXR and PFM images
+//! store linear values as they are, for compositing.
+
+use std::{
+    error::Error,
+    fmt::Display,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use exr::prelude::{
+    AnyChannel, AnyChannels, Encoding, FlatSamples, Image, Layer, LayerAttributes, SmallVec,
+    WritableImage,
+};
+use image::{codecs::png::PngEncoder, ExtendedColorType, ImageEncoder, ImageError};
+use serde::Deserialize;
+
+use crate::{
+    colour::{to_srgb16, to_srgb8},
+    framebuffer::Framebuffer,
+};
+
+#[derive(Debug)]
+pub enum OutputError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Encode {
+        path: PathBuf,
+        source: ImageError,
+    },
+    Exr {
+        path: PathBuf,
+        source: exr::error::Error,
+    },
+    UnknownFormat {
+        path: PathBuf,
+    },
+}
+
+impl Display for OutputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputError::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            OutputError::Encode { path, source } => write!(f, "{}: {source}", path.display()),
+            OutputError::Exr { path, source } => write!(f, "{}: {source}", path.display()),
+            OutputError::UnknownFormat { path } => write!(
+                f,
+                "{}: unknown image format, expected a .png, .ppm, .exr or .pfm extension",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl Error for OutputError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            OutputError::Io { source, .. } => Some(source),
+            OutputError::Encode { source, .. } => Some(source),
+            OutputError::Exr { source, .. } => Some(source),
+            OutputError::UnknownFormat { .. } => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Binary (P6) portable pixmap.
+    Ppm,
+    Png,
+    /// OpenEXR, with an alpha channel when the image has one.
+    Exr,
+    /// Portable float map, the floating-point sibling of PPM.
+    Pfm,
+}
+
+impl ImageFormat {
+    /// Picks the format from the extension of `path`, ignoring case.
+    pub fn from_path(path: &Path) -> Result<Self, OutputError> {
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("ppm") => Ok(ImageFormat::Ppm),
+            Some("png") => Ok(ImageFormat::Png),
+            Some("exr") => Ok(ImageFormat::Exr),
+            Some("pfm") => Ok(ImageFormat::Pfm),
+            _ => Err(OutputError::UnknownFormat {
+                path: path.to_path_buf(),
+            }),
+        }
+    }
+
+    /// Whether the format keeps linear floating-point values, rather than
+    /// display-encoded integers.
+    pub fn is_float(&self) -> bool {
+        matches!(self, ImageFormat::Exr | ImageFormat::Pfm)
+    }
+}
+
+/// Bits per channel of integer image formats.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "u8")]
+pub enum BitDepth {
+    #[default]
+    Eight,
+    Sixteen,
+}
+
+impl TryFrom<u8> for BitDepth {
+    type Error = String;
+
+    fn try_from(bits: u8) -> Result<Self, Self::Error> {
+        match bits {
+            8 => Ok(BitDepth::Eight),
+            16 => Ok(BitDepth::Sixteen),
+            _ => Err(format!("bit depth must be 8 or 16, not {bits}")),
+        }
+    }
+}
+
+/// Sample type of OpenEXR channels.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Precision {
+    /// 16-bit floats, plenty for colour and half the size.
+    #[default]
+    Half,
+    Float,
+}
+
+/// Writes `image` to `path` in the format its extension names. `depth` only
+/// applies to PNG and PPM, `precision` only to OpenEXR.
+pub fn write_image(
+    image: &Framebuffer,
+    path: &Path,
+    depth: BitDepth,
+    precision: Precision,
+) -> Result<(), OutputError> {
+    let io_error = |source| OutputError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    let create = || File::create(path).map(BufWriter::new).map_err(io_error);
+    match ImageFormat::from_path(path)? {
+        ImageFormat::Exr => write_exr(&[("", image)], path, precision),
+        ImageFormat::Ppm => {
+            let mut w = create()?;
+            write_ppm(image, &mut w, depth)
+                .and_then(|()| w.flush())
+                .map_err(io_error)
+        }
+        ImageFormat::Pfm => {
+            let mut w = create()?;
+            write_pfm(image, &mut w)
+                .and_then(|()| w.flush())
+                .map_err(io_error)
+        }
+        ImageFormat::Png => {
+            let mut w = create()?;
+            write_png(image, &mut w, depth).map_err(|e| match e {
+                ImageError::IoError(source) => io_error(source),
+                source => OutputError::Encode {
+                    path: path.to_path_buf(),
+                    source,
+                },
+            })?;
+            w.flush().map_err(io_error)
+        }
+    }
+}
+
+/// Writes a binary (P6) PPM. 16-bit samples are big-endian, as the format
+/// requires.
+pub fn write_ppm(image: &Framebuffer, w: &mut impl Write, depth: BitDepth) -> io::Result<()> {
+    let max = match depth {
+        BitDepth::Eight => 255,
+        BitDepth::Sixteen => 65535,
+    };
+    write!(w, "P6\n{} {}\n{max}\n", image.width(), image.height())?;
+
+    match depth {
+        BitDepth::Eight => w.write_all(&bytes8(image)),
+        BitDepth::Sixteen => {
+            let bytes: Vec<u8> = image
+                .pixels()
+                .iter()
+                .flat_map(to_srgb16)
+                .flat_map(u16::to_be_bytes)
+                .collect();
+            w.write_all(&bytes)
+        }
+    }
+}
+
+/// Writes an RGB PNG.
+pub fn write_png(
+    image: &Framebuffer,
+    w: &mut impl Write,
+    depth: BitDepth,
+) -> Result<(), ImageError> {
+    let (width, height) = (image.width() as u32, image.height() as u32);
+    let encoder = PngEncoder::new(w);
+
+    match depth {
+        BitDepth::Eight => {
+            encoder.write_image(&bytes8(image), width, height, ExtendedColorType::Rgb8)
+        }
+        BitDepth::Sixteen => {
+            // The encoder takes 16-bit samples in native byte order.
+            let bytes: Vec<u8> = image
+                .pixels()
+                .iter()
+                .flat_map(to_srgb16)
+                .flat_map(u16::to_ne_bytes)
+                .collect();
+            encoder.write_image(&bytes, width, height, ExtendedColorType::Rgb16)
+        }
+    }
+}
+
+fn bytes8(image: &Framebuffer) -> Vec<u8> {
+    image.pixels().iter().flat_map(to_srgb8).collect()
+}
+
+/// Writes a colour PFM. Its rows run from the bottom up, and the negative
+/// scale marks the samples as little-endian.
+pub fn write_pfm(image: &Framebuffer, w: &mut impl Write) -> io::Result<()> {
+    write!(w, "PF\n{} {}\n-1.0\n", image.width(), image.height())?;
+
+    let bytes: Vec<u8> = image
+        .pixels()
+        .chunks(image.width())
+        .rev()
+        .flatten()
+        .flat_map(|c| [c.x, c.y, c.z])
+        .flat_map(|v| (v as f32).to_le_bytes())
+        .collect();
+    w.write_all(&bytes)
+}
+
+/// Writes layers of the same size to one OpenEXR file. The layer named ""
+/// gets the plain `R`, `G`, `B` (and `A`) channels, the others are prefixed
+/// with their name, as in `diffuse.R`.
+pub fn write_exr(
+    layers: &[(&str, &Framebuffer)],
+    path: &Path,
+    precision: Precision,
+) -> Result<(), OutputError> {
+    let (width, height) = match layers.first() {
+        Some((_, image)) => (image.width(), image.height()),
+        None => (0, 0),
+    };
+    assert!(
+        layers
+            .iter()
+            .all(|(_, image)| (image.width(), image.height()) == (width, height)),
+        "layers differ in size"
+    );
+
+    let samples = |values: Vec<f64>| match precision {
+        Precision::Half => FlatSamples::F16(
+            values
+                .into_iter()
+                .map(exr::prelude::f16::from_f64)
+                .collect(),
+        ),
+        Precision::Float => FlatSamples::F32(values.into_iter().map(|v| v as f32).collect()),
+    };
+
+    let mut channels = Vec::new();
+    for (name, image) in layers {
+        let prefix = if name.is_empty() {
+            String::new()
+        } else {
+            format!("{name}.")
+        };
+        for (i, channel) in ["R", "G", "B"].into_iter().enumerate() {
+            let values = image.pixels().iter().map(|c| c[i]).collect();
+            channels.push(AnyChannel::new(
+                format!("{prefix}{channel}").as_str(),
+                samples(values),
+            ));
+        }
+        if let Some(alpha) = image.alpha() {
+            channels.push(AnyChannel::new(
+                format!("{prefix}A").as_str(),
+                samples(alpha.to_vec()),
+            ));
+        }
+    }
+
+    let layer = Layer::new(
+        (width, height),
+        LayerAttributes::default(),
+        Encoding::SMALL_LOSSLESS,
+        AnyChannels::sort(SmallVec::from_vec(channels)),
+    );
+    Image::from_layer(layer)
+        .write()
+        .to_file(path)
+        .map_err(|source| OutputError::Exr {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use image::ImageReader;
+
+    use super::*;
+    use crate::vec3::Colour;
+
+    fn image() -> Framebuffer {
+        Framebuffer::from_pixels(
+            2,
+            1,
+            vec![
+                Colour::new(0., 0.214_041_140_5, 1.),
+                Colour::new(2., -1., 0.5),
+            ],
+        )
+    }
+
+    #[test]
+    fn formats_from_extension() {
+        let format = |p: &str| ImageFormat::from_path(Path::new(p)).ok();
+        assert_eq!(format("out/img.PNG"), Some(ImageFormat::Png));
+        assert_eq!(format("img.ppm"), Some(ImageFormat::Ppm));
+        assert_eq!(format("img.bmp"), None);
+        assert_eq!(format("img"), None);
+
+        assert_eq!(BitDepth::try_from(16), Ok(BitDepth::Sixteen));
+        assert!(BitDepth::try_from(12).is_err());
+    }
+
+    #[test]
+    fn binary_ppm() {
+        let mut buf = Vec::new();
+        write_ppm(&image(), &mut buf, BitDepth::Eight).unwrap();
+        assert_eq!(buf, b"P6\n2 1\n255\n\x00\x80\xff\xff\x00\xbc");
+
+        let mut buf = Vec::new();
+        write_ppm(&image(), &mut buf, BitDepth::Sixteen).unwrap();
+        let (header, data) = buf.split_at(13);
+        assert_eq!(header, b"P6\n2 1\n65535\n");
+        assert_eq!(data.len(), 12);
+        assert_eq!(&data[..4], [0, 0, 0x80, 0]);
+        assert_eq!(&data[4..8], [0xff, 0xff, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let path = dir.path().join("img.png");
+        write_image(&image(), &path, BitDepth::Eight, Precision::Half).unwrap();
+        let png = ImageReader::open(&path)
+            .unwrap()
+            .decode()
+            .unwrap()
+            .to_rgb8();
+        assert_eq!(png.as_raw(), &[0, 128, 255, 255, 0, 188]);
+
+        let path = dir.path().join("deep.png");
+        write_image(&image(), &path, BitDepth::Sixteen, Precision::Half).unwrap();
+        let png = ImageReader::open(&path)
+            .unwrap()
+            .decode()
+            .unwrap()
+            .to_rgb16();
+        let expected: Vec<u16> = image().pixels().iter().flat_map(to_srgb16).collect();
+        assert_eq!(png.as_raw(), &expected);
+        assert_eq!(&expected[..3], [0, 32768, 65535]);
+
+        let err = write_image(
+            &image(),
+            &dir.path().join("img.gif"),
+            BitDepth::Eight,
+            Precision::Half,
+        );
+        assert!(matches!(err, Err(OutputError::UnknownFormat { .. })));
+    }
+
+    fn hdr_image() -> Framebuffer {
+        Framebuffer::from_pixels(
+            2,
+            2,
+            vec![
+                Colour::new(0., 0.5, 1.),
+                Colour::new(12.25, -0.125, 0.1),
+                Colour::new(1e-3, 3000., 0.75),
+                Colour::new(0.2, 0.3, 0.4),
+            ],
+        )
+        .with_alpha(vec![1., 0.5, 0.25, 0.])
+    }
+
+    /// Channels of the first layer of an OpenEXR file, by name.
+    fn read_exr(path: &Path) -> Vec<(String, Vec<f32>)> {
+        let image = exr::prelude::read_all_flat_layers_from_file(path).unwrap();
+        image.layer_data[0]
+            .channel_data
+            .list
+            .iter()
+            .map(|c| (c.name.to_string(), c.sample_data.values_as_f32().collect()))
+            .collect()
+    }
+
+    #[test]
+    fn exr_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let hdr = hdr_image();
+        let channel = |i: usize| -> Vec<f32> { hdr.pixels().iter().map(|c| c[i] as f32).collect() };
+
+        let path = dir.path().join("img.exr");
+        write_exr(&[("", &hdr)], &path, Precision::Float).unwrap();
+        let channels = read_exr(&path);
+        let names: Vec<_> = channels.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["A", "B", "G", "R"]);
+        assert_eq!(channels[0].1, [1., 0.5, 0.25, 0.]);
+        assert_eq!(channels[1].1, channel(2));
+        assert_eq!(channels[2].1, channel(1));
+        assert_eq!(channels[3].1, channel(0));
+
+        // Half floats keep about three significant digits.
+        let mut display = hdr_image();
+        display.pixels_mut()[1] = Colour::new(1., 0., 0.);
+        let path = dir.path().join("layers.exr");
+        write_exr(&[("", &hdr), ("display", &display)], &path, Precision::Half).unwrap();
+        let channels = read_exr(&path);
+        let names: Vec<_> = channels.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "A",
+                "B",
+                "G",
+                "R",
+                "display.A",
+                "display.B",
+                "display.G",
+                "display.R"
+            ]
+        );
+        for (read, written) in channels[3].1.iter().zip(channel(0)) {
+            assert!(
+                (read - written).abs() <= written.abs() / 1000.,
+                "{read} {written}"
+            );
+        }
+        assert_eq!(channels[7].1[1], 1.);
+
+        // Without alpha there is no A channel.
+        let path = dir.path().join("rgb.exr");
+        write_image(&image(), &path, BitDepth::Eight, Precision::Half).unwrap();
+        let names: Vec<_> = read_exr(&path).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["B", "G", "R"]);
+    }
+
+    #[test]
+    fn pfm_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("img.pfm");
+        write_image(&hdr_image(), &path, BitDepth::Eight, Precision::Half).unwrap();
+
+        let data = std::fs::read(&path).unwrap();
+        let (header, data) = data.split_at(12);
+        assert_eq!(header, b"PF\n2 2\n-1.0\n");
+        let values: Vec<f32> = data
+            .chunks(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+
+        // Rows are stored bottom first.
+        let pixels: Vec<_> = values
+            .chunks(3)
+            .map(|c| Colour::new(c[0] as f64, c[1] as f64, c[2] as f64))
+            .collect();
+        let (bottom, top) = pixels.split_at(2);
+        let read = Framebuffer::from_pixels(2, 2, [top, bottom].concat());
+        for (a, b) in read.pixels().iter().zip(hdr_image().pixels()) {
+            assert_eq!([a.x, a.y, a.z], [b.x, b.y, b.z].map(|v| v as f32 as f64));
+        }
+    }
+}
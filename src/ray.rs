@@ -3,6 +3,7 @@ use crate::vec3::{Point, Vec3};
 pub struct Ray {
     dir: Vec3,
     orig: Point,
+    time: f64,
 }
 
 impl Ray {
@@ -18,10 +19,21 @@ impl Ray {
         Self {
             dir: *dir,
             orig: *orig,
+            time: 0.,
         }
     }
 
     pub fn origin(&self) -> Point {
         self.orig
     }
+
+    /// Moment during the exposure at which the ray samples the scene.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn with_time(mut self, time: f64) -> Self {
+        self.time = time;
+        self
+    }
 }
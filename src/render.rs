@@ -0,0 +1,202 @@
+use rayon::prelude::*;
+
+use crate::{
+    adaptive::{noisy_pixels, PixelStats},
+    background::Background,
+    camera::{Camera, Eye, Stereo, StereoLayout},
+    constants::{BLACK, WHITE},
+    film::Film,
+    framebuffer::Framebuffer,
+    hittable::Hittable,
+    ray::Ray,
+    sampler::Sampler,
+    settings::RenderSettings,
+    vec3::Colour,
+};
+
+pub struct Renderer {
+    pub settings: RenderSettings,
+    pub background: Background,
+}
+
+impl Renderer {
+    pub fn new(settings: RenderSettings) -> Self {
+        Self {
+            settings,
+            background: Background::default(),
+        }
+    }
+
+    /// Traces `settings.samples_per_pixel` paths through every pixel and
+    /// splats their radiance onto a film through `settings.filter`, with the
+    /// fraction of paths that hit something as alpha and the number of paths
+    /// as sample counts. With `settings.adaptive`, pixels that are still noisy
+    /// then get more paths, pass after pass. Paths take their sample values
+    /// from `settings.sampler`, worked out from the pixel and sample number
+    /// alone, so with a `settings.seed` the image is the same every time,
+    /// whatever thread renders each pixel.
+    pub fn render(&self, world: &dyn Hittable, camera: &Camera) -> Framebuffer {
+        let width = self.settings.width as usize;
+        let height = self.settings.height as usize;
+        let spp = self.settings.samples_per_pixel;
+        let adaptive = self.settings.adaptive;
+        let max_samples = adaptive.map_or(spp, |a| a.max_samples(spp)) as u64;
+        let seed = self.settings.seed.unwrap_or_else(rand::random);
+
+        let film = Film::new(width, height, self.settings.filter);
+        // Rows rendered at the same time are far enough apart that their
+        // samples never land on the same pixel, so every pixel adds up its
+        // samples in the same order however the rows are spread over threads.
+        let stride = 2 * film.reach() + 1;
+        let mut stats = vec![PixelStats::default(); width * height];
+        let mut active = vec![true; width * height];
+        let mut samples = spp as u64;
+        loop {
+            for phase in 0..stride {
+                stats
+                    .par_chunks_mut(width)
+                    .zip(active.par_chunks(width))
+                    .enumerate()
+                    .filter(|(y, _)| y % stride == phase)
+                    .for_each(|(y, (stats, active))| {
+                        // Strata sized to the pass keep every pass stratified.
+                        let mut sampler = Sampler::new(self.settings.sampler, samples, seed);
+                        for (i, stats) in stats.iter_mut().enumerate().filter(|(i, _)| active[*i]) {
+                            let end = (stats.count() + samples).min(max_samples);
+                            for sample in stats.count()..end {
+                                sampler.start_sample(i as u32, y as u32, sample);
+                                let (dx, dy) = sampler.get_2d();
+                                let (c, hit) =
+                                    self.sample(world, camera, i, y, (dx, dy), &mut sampler);
+                                stats.add(c, hit);
+                                let pos = (i as f64 + dx, y as f64 + dy);
+                                film.add_sample(pos, c, hit as u8 as f64);
+                            }
+                        }
+                    });
+            }
+
+            let Some(adaptive) = adaptive else { break };
+            active = noisy_pixels(&stats, width, adaptive.threshold, max_samples);
+            if !active.contains(&true) {
+                break;
+            }
+            samples = adaptive.pass_samples(spp) as u64;
+        }
+
+        let counts = stats.iter().map(PixelStats::count).collect();
+        film.into_framebuffer().with_sample_counts(counts)
+    }
+
+    /// Radiance along the path of the sample `sampler` has started in pixel
+    /// `(i, y)`, at `(dx, dy)` from its top-left corner, and whether it hit
+    /// anything.
+    fn sample(
+        &self,
+        world: &dyn Hittable,
+        camera: &Camera,
+        i: usize,
+        y: usize,
+        (dx, dy): (f64, f64),
+        sampler: &mut Sampler,
+    ) -> (Colour, bool) {
+        let width = self.settings.width as f64;
+        let height = self.settings.height as f64;
+        // Framebuffer rows run top to bottom, v runs bottom to top. The image
+        // spans u and v from 0 to 1 edge to edge.
+        let u = (i as f64 + dx) / width;
+        let v = (height - y as f64 - dy) / height;
+        match camera.get_ray(u, v, sampler) {
+            Some(ray) => trace(
+                ray,
+                world,
+                &self.background,
+                self.settings.max_depth,
+                sampler,
+            ),
+            None => (BLACK, false),
+        }
+    }
+
+    /// Renders both eyes of a stereo pair into one image laid out as
+    /// `stereo.layout` asks, each eye getting half of the frame. The frame
+    /// must leave each eye a pixel, see [`Stereo::fits`].
+    pub fn render_stereo(
+        &self,
+        world: &dyn Hittable,
+        camera: &Camera,
+        stereo: &Stereo,
+    ) -> Framebuffer {
+        let (width, height) = (self.settings.width, self.settings.height);
+        let mut image = Framebuffer::new(width as usize, height as usize);
+        let mut offset = 0;
+        for (eye, (width, height)) in [Eye::Left, Eye::Right]
+            .into_iter()
+            .zip(stereo.eye_sizes(width, height))
+        {
+            let eye_renderer = Renderer {
+                settings: RenderSettings {
+                    width,
+                    height,
+                    ..self.settings.clone()
+                },
+                background: self.background,
+            };
+            let mut cam = camera.eye(eye, stereo);
+            cam.set_aspect_ratio(eye_renderer.settings.aspect_ratio());
+
+            let view = eye_renderer.render(world, &cam);
+            match stereo.layout {
+                StereoLayout::SideBySide => {
+                    image.paste(&view, offset, 0);
+                    offset += view.width();
+                }
+                StereoLayout::OverUnder => {
+                    image.paste(&view, 0, offset);
+                    offset += view.height();
+                }
+            }
+        }
+
+        image
+    }
+}
+
+/// Radiance carried back along `ray`, following at most `depth` bounces.
+pub fn ray_colour(
+    ray: Ray,
+    world: &dyn Hittable,
+    background: &Background,
+    depth: i32,
+    sampler: &mut Sampler,
+) -> Colour {
+    trace(ray, world, background, depth, sampler).0
+}
+
+/// Radiance along `ray`, and whether it hit anything rather than escaping to
+/// the background.
+fn trace(
+    ray: Ray,
+    world: &dyn Hittable,
+    background: &Background,
+    depth: i32,
+    sampler: &mut Sampler,
+) -> (Colour, bool) {
+    if depth <= 0 {
+        return (BLACK, false);
+    }
+
+    if let Some(rec) = world.hit(&ray, 0.001, f64::INFINITY) {
+        let emitted = rec.mat.emitted(&rec);
+        let mut attenuation = WHITE;
+        sampler.start_bounce();
+        if let Some(scattered_ray) = rec.mat.scatter(&ray, &rec, &mut attenuation, sampler) {
+            let incoming = ray_colour(scattered_ray, world, background, depth - 1, sampler);
+            return (emitted + attenuation * incoming, true);
+        }
+
+        return (emitted, true);
+    }
+
+    (background.colour(&ray), false)
+}
@@ -0,0 +1,500 @@
+//! Sample generators. Every camera path takes its pixel position, lens
+//! position, time and each bounce's scattering decisions from a `Sampler`,
+//! which can spread the samples of a pixel more evenly than independent
+//! random numbers and so converge faster.
+//!
+//! Values are worked out from the pixel, the sample number and the
+//! dimension alone, so they do not depend on the order pixels are rendered
+//! in.
+
+use std::{f64::consts::PI, sync::OnceLock};
+
+use serde::Deserialize;
+
+use crate::{
+    rng::{mix, Rng},
+    vec3::Vec3,
+};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplerKind {
+    /// Independent uniform random numbers.
+    #[default]
+    Random,
+    /// One jittered sample per stratum, with the strata shuffled separately
+    /// in every dimension.
+    Stratified,
+    /// The Halton sequence, with a prime base per dimension and Owen
+    /// scrambling. Dimensions past the prime table, from the tenth bounce
+    /// on, take independent random numbers.
+    Halton,
+    /// Sobol's (0, 2)-sequence with hash-based Owen scrambling, shuffled to
+    /// pad it out to any number of dimensions.
+    Sobol,
+    /// One scrambled Sobol sequence for the whole image, shifted in every
+    /// pixel by a blue noise mask so that what error remains is fine-grained
+    /// and easy on the eye.
+    BlueNoise,
+}
+
+/// Dimensions the camera takes: pixel position (2), time (1) and lens (2).
+const CAMERA_DIMENSIONS: u32 = 5;
+/// Dimensions each bounce may take: a direction (2) and a choice (1).
+const BOUNCE_DIMENSIONS: u32 = 3;
+
+/// Hands out the sample values of one camera path at a time.
+#[derive(Clone, Debug)]
+pub struct Sampler {
+    kind: SamplerKind,
+    samples_per_pixel: u64,
+    seed: u64,
+    pixel: (u32, u32),
+    index: u64,
+    dimension: u32,
+    bounce: u32,
+    rng: Rng,
+}
+
+impl Sampler {
+    pub fn new(kind: SamplerKind, samples_per_pixel: u64, seed: u64) -> Self {
+        Self {
+            kind,
+            samples_per_pixel: samples_per_pixel.max(1),
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+            bounce: 0,
+            rng: Rng::new(seed, 0),
+        }
+    }
+
+    pub fn kind(&self) -> SamplerKind {
+        self.kind
+    }
+
+    /// Starts sample `index` of pixel `(x, y)`, from its first dimension. Any
+    /// `samples_per_pixel` consecutive samples fill the strata once.
+    pub fn start_sample(&mut self, x: u32, y: u32, index: u64) {
+        self.pixel = (x, y);
+        self.index = index;
+        self.dimension = 0;
+        self.bounce = 0;
+        let pixel = (y as u64) << 32 | x as u64;
+        self.rng = Rng::for_sample(self.seed, pixel, index);
+    }
+
+    /// Moves on to the dimensions of the path's next bounce. Bounces get
+    /// fixed dimensions whatever the camera and earlier bounces used.
+    pub fn start_bounce(&mut self) {
+        self.dimension = CAMERA_DIMENSIONS + BOUNCE_DIMENSIONS * self.bounce;
+        self.bounce += 1;
+    }
+
+    /// Next value in `[0, 1)`.
+    pub fn get_1d(&mut self) -> f64 {
+        let d = self.dimension;
+        self.dimension += 1;
+
+        match self.kind {
+            SamplerKind::Random => self.rng.random(),
+            SamplerKind::Stratified => {
+                let n = self.samples_per_pixel.min(u32::MAX as u64) as u32;
+                let stratum = permute((self.index % n as u64) as u32, n, self.hash(d, 0) as u32);
+                (stratum as f64 + self.jitter(d, 0)) / n as f64
+            }
+            SamplerKind::Halton => self.halton(d),
+            SamplerKind::Sobol => {
+                let i = shuffle(self.index, self.hash(d, 1));
+                to_unit(owen_scramble(i.reverse_bits(), self.hash(d, 2)))
+            }
+            SamplerKind::BlueNoise => {
+                let i = shuffle(self.index, self.image_hash(d, 1));
+                let x = to_unit(owen_scramble(i.reverse_bits(), self.image_hash(d, 2)));
+                (x + self.blue_noise(d)).fract()
+            }
+        }
+    }
+
+    /// Next pair of values in `[0, 1)`, spread evenly over the square.
+    pub fn get_2d(&mut self) -> (f64, f64) {
+        let d = self.dimension;
+        self.dimension += 2;
+
+        match self.kind {
+            SamplerKind::Random => (self.rng.random(), self.rng.random()),
+            SamplerKind::Stratified => {
+                let n = self.samples_per_pixel.min(u32::MAX as u64) as u32;
+                let nx = (n as f64).sqrt() as u32;
+                let ny = n / nx;
+                let cells = nx * ny;
+                let cell = permute(
+                    (self.index % cells as u64) as u32,
+                    cells,
+                    self.hash(d, 0) as u32,
+                );
+                (
+                    ((cell % nx) as f64 + self.jitter(d, 0)) / nx as f64,
+                    ((cell / nx) as f64 + self.jitter(d, 1)) / ny as f64,
+                )
+            }
+            SamplerKind::Halton => (self.halton(d), self.halton(d + 1)),
+            SamplerKind::Sobol => {
+                let i = shuffle(self.index, self.hash(d, 1));
+                sobol_2d(i, self.hash(d, 2), self.hash(d, 3))
+            }
+            SamplerKind::BlueNoise => {
+                let i = shuffle(self.index, self.image_hash(d, 1));
+                let (x, y) = sobol_2d(i, self.image_hash(d, 2), self.image_hash(d, 3));
+                (
+                    (x + self.blue_noise(d)).fract(),
+                    (y + self.blue_noise(d + 1)).fract(),
+                )
+            }
+        }
+    }
+
+    /// Random numbers for anything past what the sampler plans for, such as
+    /// retries when rejection sampling.
+    pub fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    /// Hash of the pixel, the dimension and `salt`.
+    fn hash(&self, dimension: u32, salt: u64) -> u64 {
+        let (x, y) = self.pixel;
+        mix(self.image_hash(dimension, salt) ^ mix((y as u64) << 32 | x as u64))
+    }
+
+    /// Hash of the dimension and `salt`, the same in every pixel.
+    fn image_hash(&self, dimension: u32, salt: u64) -> u64 {
+        mix(self.seed ^ mix((dimension as u64) << 8 | salt))
+    }
+
+    /// Offset within a stratum, different for every sample.
+    fn jitter(&self, dimension: u32, salt: u64) -> f64 {
+        to_unit((mix(self.hash(dimension, salt) ^ self.index) >> 32) as u32)
+    }
+
+    /// Halton value in dimension `d`. Reusing bases past the table would
+    /// correlate dimensions, so those fall back to random numbers.
+    fn halton(&mut self, d: u32) -> f64 {
+        match PRIMES.get(d as usize) {
+            Some(&base) => owen_radical_inverse(self.index, base, self.hash(d, 0)),
+            None => self.rng.random(),
+        }
+    }
+
+    /// The blue noise mask at this pixel, shifted by an amount of its own
+    /// for each dimension.
+    fn blue_noise(&self, dimension: u32) -> f64 {
+        let shift = self.image_hash(dimension, 4);
+        let (x, y) = self.pixel;
+        let x = (x as usize + shift as usize) % MASK_SIZE;
+        let y = (y as usize + (shift >> 32) as usize) % MASK_SIZE;
+        blue_noise_mask()[y * MASK_SIZE + x]
+    }
+}
+
+/// Uniform direction on the unit sphere.
+pub fn sphere((u, v): (f64, f64)) -> Vec3 {
+    let z = 1. - 2. * u;
+    let r = (1. - z * z).max(0.).sqrt();
+    let phi = 2. * PI * v;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Uniform point in the unit ball, with `w` picking the distance out.
+pub fn ball(u: (f64, f64), w: f64) -> Vec3 {
+    w.cbrt() * sphere(u)
+}
+
+/// Uniform point in the unit disk of the xy plane, by Shirley and Chiu's
+/// concentric mapping, which keeps nearby samples nearby.
+pub fn disk((u, v): (f64, f64)) -> Vec3 {
+    let (x, y) = (2. * u - 1., 2. * v - 1.);
+    if x == 0. && y == 0. {
+        return Vec3::default();
+    }
+
+    let (r, theta) = if x.abs() > y.abs() {
+        (x, PI / 4. * (y / x))
+    } else {
+        (y, PI / 2. - PI / 4. * (x / y))
+    };
+    Vec3::new(r * theta.cos(), r * theta.sin(), 0.)
+}
+
+const PRIMES: [u64; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+fn to_unit(x: u32) -> f64 {
+    x as f64 / (1u64 << 32) as f64
+}
+
+/// Element `i` of a random permutation of `0..n`, picked by `seed`
+/// (Kensler's hash-based permutation).
+fn permute(mut i: u32, n: u32, seed: u32) -> u32 {
+    let mut w = n.saturating_sub(1);
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+
+    let p = seed;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < n {
+            break;
+        }
+    }
+    (i.wrapping_add(p)) % n.max(1)
+}
+
+/// Radical inverse of `a` in `base`, with every digit permuted by a hash of
+/// the digits before it: Owen scrambling. Digits stop at the 32 bits of
+/// resolution the other samplers have.
+fn owen_radical_inverse(mut a: u64, base: u64, hash: u64) -> f64 {
+    let inv_base = 1. / base as f64;
+    let mut reversed = 0u64;
+    let mut scale = 1.;
+    while scale > 1. / (1u64 << 32) as f64 {
+        let digit = a % base;
+        a /= base;
+        let digit = permute(digit as u32, base as u32, mix(hash ^ reversed) as u32);
+        reversed = reversed * base + digit as u64;
+        scale *= inv_base;
+    }
+    (reversed as f64 * scale).min(1. - f64::EPSILON)
+}
+
+/// Owen scrambling of the bits of `x`, most significant first, after Laine
+/// and Karras as improved by Burley.
+fn owen_scramble(x: u32, seed: u64) -> u32 {
+    let mut x = x.reverse_bits();
+    x = x.wrapping_add(seed as u32);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x.reverse_bits()
+}
+
+/// Shuffles sample numbers so that each padded dimension visits the
+/// sequence in its own order, while keeping its stratification.
+fn shuffle(index: u64, seed: u64) -> u32 {
+    owen_scramble(index as u32, seed)
+}
+
+/// The first two dimensions of the Sobol sequence at `i`, each Owen
+/// scrambled.
+fn sobol_2d(i: u32, seed_x: u64, seed_y: u64) -> (f64, f64) {
+    let x = i.reverse_bits();
+    let mut y = 0;
+    let mut v = 1u32 << 31;
+    let mut bits = i;
+    while bits != 0 {
+        if bits & 1 != 0 {
+            y ^= v;
+        }
+        bits >>= 1;
+        v ^= v >> 1;
+    }
+
+    (
+        to_unit(owen_scramble(x, seed_x)),
+        to_unit(owen_scramble(y, seed_y)),
+    )
+}
+
+const MASK_SIZE: usize = 64;
+
+/// A tileable blue noise mask: values in `[0, 1)` whose neighbours are as
+/// different from each other as possible. Built once by placing pixels one
+/// at a time in the largest void left by those before (Ulichney's
+/// void-and-cluster), and ranking them in that order.
+fn blue_noise_mask() -> &'static [f64] {
+    static MASK: OnceLock<Vec<f64>> = OnceLock::new();
+    MASK.get_or_init(|| {
+        let n = MASK_SIZE;
+        let wrap = |d: usize| d.min(n - d) as f64;
+        let kernel: Vec<f64> = (0..n * n)
+            .map(|i| {
+                let (dx, dy) = (wrap(i % n), wrap(i / n));
+                (-(dx * dx + dy * dy) / (2. * 1.5 * 1.5)).exp()
+            })
+            .collect();
+
+        let mut energy = vec![0f64; n * n];
+        let mut rank = vec![f64::NAN; n * n];
+        for r in 0..n * n {
+            let void = (0..n * n)
+                .filter(|&i| rank[i].is_nan())
+                .min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+                .unwrap();
+            rank[void] = (r as f64 + 0.5) / (n * n) as f64;
+
+            let (vx, vy) = (void % n, void / n);
+            for (i, e) in energy.iter_mut().enumerate() {
+                let (dx, dy) = ((i % n + n - vx) % n, (i / n + n - vy) % n);
+                *e += kernel[dy * n + dx];
+            }
+        }
+        rank
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [SamplerKind; 5] = [
+        SamplerKind::Random,
+        SamplerKind::Stratified,
+        SamplerKind::Halton,
+        SamplerKind::Sobol,
+        SamplerKind::BlueNoise,
+    ];
+
+    fn path(sampler: &mut Sampler, x: u32, y: u32, index: u64) -> Vec<f64> {
+        sampler.start_sample(x, y, index);
+        let (a, b) = sampler.get_2d();
+        let c = sampler.get_1d();
+        sampler.start_bounce();
+        let (d, e) = sampler.get_2d();
+        vec![a, b, c, d, e]
+    }
+
+    #[test]
+    fn repeatable_values_in_unit_interval() {
+        for kind in KINDS {
+            let mut sampler = Sampler::new(kind, 16, 3);
+            for index in 0..64 {
+                let values = path(&mut sampler, 5, 7, index);
+                assert!(values.iter().all(|v| (0. ..1.).contains(v)), "{kind:?}");
+                assert_eq!(values, path(&mut sampler, 5, 7, index), "{kind:?}");
+            }
+            assert_ne!(path(&mut sampler, 5, 7, 1), path(&mut sampler, 6, 7, 1));
+        }
+    }
+
+    #[test]
+    fn stratified_pixels() {
+        // 16 samples fill every cell of a 4x4 grid once, in every dimension.
+        for kind in [SamplerKind::Stratified, SamplerKind::Sobol] {
+            let mut sampler = Sampler::new(kind, 16, 9);
+            for dimension in 0..4 {
+                let mut cells = [0; 16];
+                for index in 0..16 {
+                    sampler.start_sample(2, 3, index);
+                    (0..dimension).for_each(|_| sampler.start_bounce());
+                    let (u, v) = sampler.get_2d();
+                    cells[(4. * v) as usize * 4 + (4. * u) as usize] += 1;
+                }
+                assert_eq!(cells, [1; 16], "{kind:?}");
+            }
+        }
+
+        // So do any 16 consecutive stratified samples, as adaptive passes take.
+        let mut sampler = Sampler::new(SamplerKind::Stratified, 16, 9);
+        let mut cells = [0; 16];
+        for index in 40..56 {
+            sampler.start_sample(2, 3, index);
+            let (u, v) = sampler.get_2d();
+            cells[(4. * v) as usize * 4 + (4. * u) as usize] += 1;
+        }
+        assert_eq!(cells, [1; 16]);
+
+        // The first dimension of Halton is base 2.
+        let mut sampler = Sampler::new(SamplerKind::Halton, 16, 9);
+        let mut cells = [0; 16];
+        for index in 0..16 {
+            sampler.start_sample(2, 3, index);
+            cells[(16. * sampler.get_1d()) as usize] += 1;
+        }
+        assert_eq!(cells, [1; 16]);
+    }
+
+    /// Root mean square error, over many pixels, of estimating the mean of
+    /// a smooth function over the square from `spp` samples.
+    fn rms_error(kind: SamplerKind, spp: u64) -> f64 {
+        let f = |(u, v): (f64, f64)| (u * v).sin() + u * u;
+        let exact = 0.239_811_742_000_564_73 + 1. / 3.;
+
+        let mut sampler = Sampler::new(kind, spp, 1);
+        let pixels = 256;
+        let sum: f64 = (0..pixels)
+            .map(|x| {
+                let mean = (0..spp)
+                    .map(|i| {
+                        sampler.start_sample(x, 0, i);
+                        f(sampler.get_2d())
+                    })
+                    .sum::<f64>()
+                    / spp as f64;
+                (mean - exact).powi(2)
+            })
+            .sum();
+        (sum / pixels as f64).sqrt()
+    }
+
+    #[test]
+    fn converges_faster_than_random() {
+        let random = rms_error(SamplerKind::Random, 64);
+        assert!(random > 0.02 && random < 0.1, "{random}");
+        for kind in &KINDS[1..] {
+            let error = rms_error(*kind, 64);
+            assert!(error < random / 3., "{kind:?}: {error} against {random}");
+        }
+    }
+
+    #[test]
+    fn warps() {
+        for (u, v) in [(0., 0.), (0.3, 0.9), (0.999, 0.5)] {
+            assert!((sphere((u, v)).len() - 1.).abs() < 1e-12);
+            assert!(ball((u, v), 0.5).len() <= 1.);
+            let p = disk((u, v));
+            assert!(p.len() <= 1. + 1e-12 && p.z == 0.);
+        }
+        // The disk's corners land on its edge, its centre in the middle.
+        assert!((disk((1., 1.)).len() - 1.).abs() < 1e-12);
+        assert_eq!(disk((0.5, 0.5)).len(), 0.);
+    }
+
+    #[test]
+    fn blue_noise() {
+        let mask = blue_noise_mask();
+        let mut sorted = mask.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        assert!(sorted.windows(2).all(|w| w[0] < w[1]));
+
+        // Neighbours differ by more than the 1/3 of white noise.
+        let n = MASK_SIZE;
+        let diff = (0..n * n)
+            .map(|i| (mask[i] - mask[(i / n) * n + (i + 1) % n]).abs())
+            .sum::<f64>()
+            / (n * n) as f64;
+        assert!(diff > 0.38, "{diff}");
+    }
+}
@@ -0,0 +1,1500 @@
+//! TOML scene description.
+//!
+//! ```toml
+//! [camera]
+//! lookfrom = [13, 2, 3]
+//! lookat = [0, 0, 0]
+//! vfov = 20
+//! aperture = 0.1
+//! focus_dist = 10
+//!
+//! [render]
+//! width = 400
+//! aspect_ratio = 1.5
+//! samples_per_pixel = 100
+//! max_depth = 50
+//! output = "img.png"
+//! bit_depth = 8
+//!
+//! [background]
+//! type = "gradient"
+//! bottom = [1, 1, 1]
+//! top = [0.5, 0.7, 1]
+//!
+//! [textures.checks]
+//! type = "checker"
+//! scale = 0.5
+//! even = [0.2, 0.3, 0.1]
+//! odd = [0.9, 0.9, 0.9]
+//!
+//! [materials.ground]
+//! type = "lambertian"
+//! albedo = "checks"
+//!
+//! [materials.lamp]
+//! type = "diffuse_light"
+//! emit = [4, 4, 4]
+//!
+//! [[objects]]
+//! type = "sphere"
+//! centre = [0, -1000, 0]
+//! radius = 1000
+//! material = "ground"
+//!
+//! [[objects]]
+//! type = "obj"
+//! path = "models/teapot.obj"
+//! ```
+//!
+//! `autofocus = [x, y]` in `[camera]` overrides `focus_dist` with the depth
+//! of whatever is seen through that pixel, counted from the top-left corner.
+//! The pixel moves with the image when `--width` or `--height` resize it.
+//!
+//! Bokeh shapes come from a `[camera.bokeh]` table: `type = "polygon"` with
+//! `blades` and optional `rotation` (degrees) and `rounding` (0 to 1), or
+//! `type = "mask"` with the `path` of an image. `anamorphic_squeeze` stretches
+//! bokeh vertically and `vignetting` clips it into cat's eyes towards the
+//! edges of the frame.
+//!
+//! Other projections are set with a `[camera.projection]` table:
+//! `type = "orthographic"` with a view `height`, `type = "fisheye"` with a
+//! `fov` in degrees and `mapping = "equidistant" | "equisolid"`,
+//! `type = "equirectangular"` for 360 degree panoramas, or
+//! `type = "cube_face"` with `face = "front" | "back" | "left" | "right" |
+//! "up" | "down"`.
+//!
+//! A `[camera.stereo]` table renders a stereo pair into one image, with
+//! `layout = "side_by_side" | "over_under"`, the `interocular` distance and an
+//! optional `convergence` distance (parallel eyes when omitted). With the
+//! equirectangular projection this gives omni-directional stereo panoramas.
+//!
+//! `shutter = [open, close]` in `[camera]` blurs anything moving in between.
+//! Spheres, triangles and OBJ models take an optional `motion` list of
+//! keyframes `{ time, translate, rotate, scale }` (rotation in degrees about
+//! x, then y, then z), and `type = "moving_sphere"` objects follow
+//! `keyframes = [{ time, centre }, ...]`. Both take `interpolation =
+//! "linear" | "bezier"`.
+//!
+//! A `[camera.exposure]` table sets the camera up photographically:
+//! `f_number` (giving the aperture from the focal length), `shutter_speed` in
+//! seconds (which also opens the shutter for motion blur from time 0), `iso`,
+//! `compensation` in stops, the `white_balance` colour temperature in kelvin
+//! and the `sensor_height` in millimetres. Light is then in cd/m² and scene
+//! units in metres; the defaults are f/16, 1/100 s and ISO 100, for daylight.
+//!
+//! A `[tone_mapping]` table compresses highlights instead of clipping them,
+//! with `operator = "reinhard" | "extended_reinhard" | "aces_filmic" |
+//! "hable" | "agx"`, an `exposure` adjustment in stops and, for the extended
+//! Reinhard and Hable curves, the `white` value that maps to white.
+//!
+//! `adaptive = { threshold = 0.02 }` in `[render]` keeps sampling pixels
+//! whose standard error is above `threshold` times their brightness, in passes
+//! of `samples_per_pass` (`samples_per_pixel` by default), up to
+//! `max_samples_per_pixel` (16 times `samples_per_pixel` by default).
+//! `sample_map = "samples.png"` also writes the samples each pixel took,
+//! scaled to the most any pixel took (raw counts in OpenEXR and PFM).
+//!
+//! `seed` in `[render]` makes renders reproducible, `sampler = "random" |
+//! "stratified" | "halton" | "sobol" | "blue_noise"` picks how the samples of
+//! a pixel are spread out (low-discrepancy samplers converge faster than the
+//! default independent random numbers), and `colour_space =
+//! "srgb" | "aces_cg"` picks the linear space light is traced in. Colours and
+//! textures are always given in sRGB.
+//!
+//! `filter = { type = "mitchell", radius = 2 }` weights each sample into the
+//! pixels around it, with `type = "box" | "tent" | "gaussian" | "mitchell" |
+//! "lanczos" | "blackman_harris"` and a `radius` in pixels (0.5, 1, 1.5, 2,
+//! 3 and 1.5 by default respectively). The default box of radius 0.5 averages
+//! each pixel's own samples.
+//!
+//! The `output` extension picks the image format. PNG (`bit_depth = 8 | 16`)
+//! and PPM images are tone mapped and sRGB encoded. OpenEXR and PFM images
+//! keep linear sRGB as rendered and exposed, before tone mapping; OpenEXR
+//! adds the fraction of each pixel covered by objects as alpha, stores
+//! `precision = "half" | "float"` samples and, with `display_layer = true`,
+//! also the tone mapped image as a `display` layer.
+//!
+//! An `[animation]` table renders `frames` frames at `fps` (24 by default)
+//! from scene time `start`, numbering the output files: `output = "frame.png"`
+//! writes `frame_0001.png`, `frame_0002.png`, ... Its `camera` list keys any
+//! of `lookfrom`, `lookat`, `vfov` and `focus_dist` at a `time`; the rest hold
+//! their `[camera]` values. `fixed_seed = true` reuses the same random
+//! numbers (the `[render]` `seed`) for every frame so that noise does not
+//! flicker.
+//!
+//! The background may also be `type = "solid"` with a `colour`, or
+//! `type = "none"` for scenes lit only by emissive materials. Material
+//! colours (`albedo`, `emit`) are either an RGB triple or the name of a
+//! texture. Textures and materials are shared by every material or object
+//! naming them.
+//!
+//! Image textures are `type = "image"` with a `path`, and optionally
+//! `wrap = "repeat" | "clamp" | "mirror"` and `filter = "bilinear" | "nearest"`.
+//! Image and OBJ paths are relative to the scene file.
+//!
+//! Procedural textures are `type = "noise"` with a `pattern` of `"perlin"`,
+//! `"fbm"`, `"marble"`, `"wood"` or `"worley"`, blending from `low` to `high`
+//! (black and white by default). `scale`, `seed`, `octaves` and `turbulence`
+//! are optional.
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::Display,
+    fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::{
+    adaptive::AdaptiveSampling,
+    animation::{Animation, CameraPath},
+    aperture::{ApertureMask, ApertureShape},
+    background::Background,
+    camera::{Camera, Projection, Stereo},
+    colour::ColourSpace,
+    exposure::Exposure,
+    filter::PixelFilter,
+    hittable::{Hittable, HittableList},
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    motion::{Interpolation, Keyframes, Lerp},
+    obj::{load_obj_in, ObjError},
+    output::{BitDepth, Precision},
+    sampler::SamplerKind,
+    settings::{RenderSettings, DEFAULT_ASPECT_RATIO},
+    sphere::{MovingSphere, Sphere},
+    texture::{
+        Checker, Filter, ImageTexture, NoisePattern, NoiseTexture, SolidColour, Texture,
+        TextureError, WrapMode,
+    },
+    tonemap::ToneMapping,
+    transform::{Transform, Transformed},
+    triangle::Triangle,
+    vec3::Vec3,
+};
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Invalid {
+        file: String,
+        line: usize,
+        column: usize,
+        msg: String,
+    },
+    Obj(ObjError),
+    Texture(TextureError),
+}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneError::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            SceneError::Invalid {
+                file,
+                line,
+                column,
+                msg,
+            } => write!(f, "{file}:{line}:{column}: {msg}"),
+            SceneError::Obj(e) => e.fmt(f),
+            SceneError::Texture(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneError::Io { source, .. } => Some(source),
+            SceneError::Obj(e) => Some(e),
+            SceneError::Texture(e) => Some(e),
+            SceneError::Invalid { .. } => None,
+        }
+    }
+}
+
+impl From<ObjError> for SceneError {
+    fn from(e: ObjError) -> Self {
+        SceneError::Obj(e)
+    }
+}
+
+impl From<TextureError> for SceneError {
+    fn from(e: TextureError) -> Self {
+        SceneError::Texture(e)
+    }
+}
+
+pub struct Scene {
+    pub world: HittableList,
+    pub camera: Camera,
+    pub settings: RenderSettings,
+    pub background: Background,
+    /// Pixel to focus on once the final resolution is known.
+    pub focus_pixel: Option<(u32, u32)>,
+    pub stereo: Option<Stereo>,
+    pub animation: Option<Animation>,
+    /// Applied to the rendered image, before it is written.
+    pub exposure: Option<Exposure>,
+    pub tone_mapping: ToneMapping,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDesc {
+    camera: CameraDesc,
+    #[serde(default)]
+    render: RenderDesc,
+    background: Option<BackgroundDesc>,
+    #[serde(default)]
+    textures: HashMap<String, Spanned<TextureDesc>>,
+    #[serde(default)]
+    materials: HashMap<String, Spanned<MaterialDesc>>,
+    #[serde(default)]
+    objects: Vec<Spanned<ObjectDesc>>,
+    animation: Option<AnimationDesc>,
+    tone_mapping: Option<Spanned<ToneMapping>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDesc {
+    lookfrom: [f64; 3],
+    lookat: [f64; 3],
+    #[serde(default = "default_vup")]
+    vup: [f64; 3],
+    vfov: Spanned<f64>,
+    aperture: Option<Spanned<f64>>,
+    focus_dist: Option<Spanned<f64>>,
+    autofocus: Option<Spanned<[u32; 2]>>,
+    bokeh: Option<Spanned<BokehDesc>>,
+    anamorphic_squeeze: Option<Spanned<f64>>,
+    vignetting: Option<Spanned<f64>>,
+    projection: Option<Spanned<Projection>>,
+    stereo: Option<Spanned<Stereo>>,
+    shutter: Option<Spanned<[f64; 2]>>,
+    exposure: Option<Spanned<Exposure>>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum BokehDesc {
+    Circle,
+    Polygon {
+        blades: u32,
+        #[serde(default)]
+        rotation: f64,
+        #[serde(default)]
+        rounding: f64,
+    },
+    Mask {
+        path: PathBuf,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AnimationDesc {
+    frames: Spanned<u32>,
+    fps: Option<Spanned<f64>>,
+    #[serde(default)]
+    start: f64,
+    #[serde(default)]
+    interpolation: Interpolation,
+    #[serde(default)]
+    fixed_seed: bool,
+    #[serde(default)]
+    camera: Vec<Spanned<CameraKeyDesc>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraKeyDesc {
+    time: f64,
+    lookfrom: Option<[f64; 3]>,
+    lookat: Option<[f64; 3]>,
+    vfov: Option<f64>,
+    focus_dist: Option<f64>,
+}
+
+fn default_vup() -> [f64; 3] {
+    [0., 1., 0.]
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RenderDesc {
+    width: Option<Spanned<i32>>,
+    height: Option<Spanned<i32>>,
+    aspect_ratio: Option<Spanned<f64>>,
+    samples_per_pixel: Option<Spanned<i64>>,
+    max_depth: Option<Spanned<i32>>,
+    adaptive: Option<Spanned<AdaptiveSampling>>,
+    output: Option<PathBuf>,
+    sample_map: Option<PathBuf>,
+    seed: Option<u64>,
+    #[serde(default)]
+    sampler: SamplerKind,
+    filter: Option<Spanned<PixelFilter>>,
+    #[serde(default)]
+    colour_space: ColourSpace,
+    #[serde(default)]
+    bit_depth: BitDepth,
+    #[serde(default)]
+    precision: Precision,
+    #[serde(default)]
+    display_layer: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum TextureDesc {
+    Solid {
+        colour: [f64; 3],
+    },
+    Checker {
+        scale: f64,
+        even: [f64; 3],
+        odd: [f64; 3],
+    },
+    Image {
+        path: PathBuf,
+        #[serde(default)]
+        wrap: WrapMode,
+        #[serde(default)]
+        filter: Filter,
+    },
+    Noise {
+        pattern: NoisePattern,
+        #[serde(default = "default_scale")]
+        scale: f64,
+        #[serde(default)]
+        seed: u64,
+        octaves: Option<u32>,
+        turbulence: Option<f64>,
+        #[serde(default)]
+        low: [f64; 3],
+        #[serde(default = "default_high")]
+        high: [f64; 3],
+    },
+}
+
+fn default_scale() -> f64 {
+    1.
+}
+
+fn default_high() -> [f64; 3] {
+    [1., 1., 1.]
+}
+
+/// A constant colour or the name of a texture.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColourDesc {
+    Colour([f64; 3]),
+    Texture(String),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDesc {
+    Lambertian {
+        albedo: ColourDesc,
+    },
+    Metal {
+        albedo: ColourDesc,
+        #[serde(default)]
+        fuzz: f64,
+    },
+    Dielectric {
+        ir: f64,
+    },
+    DiffuseLight {
+        emit: ColourDesc,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum BackgroundDesc {
+    None,
+    Solid { colour: [f64; 3] },
+    Gradient { bottom: [f64; 3], top: [f64; 3] },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectDesc {
+    Sphere {
+        centre: [f64; 3],
+        radius: f64,
+        material: String,
+        #[serde(default)]
+        motion: Vec<TransformKeyDesc>,
+        #[serde(default)]
+        interpolation: Interpolation,
+    },
+    MovingSphere {
+        keyframes: Vec<CentreKeyDesc>,
+        radius: f64,
+        material: String,
+        #[serde(default)]
+        interpolation: Interpolation,
+    },
+    Triangle {
+        vertices: [[f64; 3]; 3],
+        material: String,
+        #[serde(default)]
+        motion: Vec<TransformKeyDesc>,
+        #[serde(default)]
+        interpolation: Interpolation,
+    },
+    Obj {
+        path: PathBuf,
+        #[serde(default)]
+        motion: Vec<TransformKeyDesc>,
+        #[serde(default)]
+        interpolation: Interpolation,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CentreKeyDesc {
+    time: f64,
+    centre: [f64; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TransformKeyDesc {
+    time: f64,
+    #[serde(default)]
+    translate: [f64; 3],
+    #[serde(default)]
+    rotate: [f64; 3],
+    #[serde(default = "default_scale")]
+    scale: f64,
+}
+
+fn vec3(v: &[f64; 3]) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+struct Source<'a> {
+    src: &'a str,
+    file: &'a str,
+}
+
+impl Source<'_> {
+    fn error(&self, span: Range<usize>, msg: impl Into<String>) -> SceneError {
+        let before = &self.src[..span.start.min(self.src.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+
+        SceneError::Invalid {
+            file: self.file.to_string(),
+            line,
+            column,
+            msg: msg.into(),
+        }
+    }
+
+    fn check<T: Copy>(
+        &self,
+        value: &Spanned<T>,
+        valid: impl Fn(T) -> bool,
+        msg: &str,
+    ) -> Result<T, SceneError> {
+        if valid(*value.get_ref()) {
+            Ok(*value.get_ref())
+        } else {
+            Err(self.error(value.span(), msg))
+        }
+    }
+}
+
+/// Parses a scene description. Relative image and OBJ paths are resolved
+/// against `base_dir`.
+pub fn parse_scene(src: &str, file: &str, base_dir: &Path) -> Result<Scene, SceneError> {
+    let source = Source { src, file };
+    let desc: SceneDesc = toml::from_str(src).map_err(|e| {
+        source.error(
+            e.span().unwrap_or(0..0),
+            e.message().trim_end().replace('\n', ": "),
+        )
+    })?;
+
+    let settings = render_settings(&source, &desc.render)?;
+    let camera = camera(&source, &desc.camera, settings.aspect_ratio(), base_dir)?;
+
+    let mut textures: HashMap<&str, Arc<dyn Texture>> = HashMap::new();
+    for (name, tex) in &desc.textures {
+        let tex: Arc<dyn Texture> = match tex.get_ref() {
+            TextureDesc::Solid { colour } => Arc::new(SolidColour::new(&vec3(colour))),
+            TextureDesc::Checker { scale, even, odd } => {
+                if *scale <= 0. {
+                    return Err(source.error(tex.span(), "checker scale must be positive"));
+                }
+                Arc::new(Checker::from_colours(*scale, &vec3(even), &vec3(odd)))
+            }
+            TextureDesc::Image { path, wrap, filter } => Arc::new(
+                ImageTexture::load(base_dir.join(path))?
+                    .with_wrap(*wrap)
+                    .with_filter(*filter),
+            ),
+            TextureDesc::Noise {
+                pattern,
+                scale,
+                seed,
+                octaves,
+                turbulence,
+                low,
+                high,
+            } => {
+                if *scale <= 0. {
+                    return Err(source.error(tex.span(), "noise scale must be positive"));
+                }
+                let mut noise = NoiseTexture::new(*pattern, *scale, *seed)
+                    .with_colours(&vec3(low), &vec3(high));
+                if let Some(octaves) = octaves {
+                    noise = noise.with_octaves(*octaves);
+                }
+                if let Some(turbulence) = turbulence {
+                    noise = noise.with_turbulence(*turbulence);
+                }
+                Arc::new(noise)
+            }
+        };
+        textures.insert(name, tex);
+    }
+
+    let mut materials: HashMap<&str, Arc<dyn Material>> = HashMap::new();
+    for (name, mat) in &desc.materials {
+        let texture = |c: &ColourDesc| -> Result<Arc<dyn Texture>, SceneError> {
+            let texture: Arc<dyn Texture> = match c {
+                ColourDesc::Colour(c) => Arc::new(SolidColour::new(&vec3(c))),
+                ColourDesc::Texture(name) => textures
+                    .get(name.as_str())
+                    .cloned()
+                    .ok_or_else(|| source.error(mat.span(), format!("unknown texture `{name}`")))?,
+            };
+            Ok(settings.colour_space.texture(texture))
+        };
+
+        let mat: Arc<dyn Material> = match mat.get_ref() {
+            MaterialDesc::Lambertian { albedo } => {
+                Arc::new(Lambertian::with_texture(texture(albedo)?))
+            }
+            MaterialDesc::Metal { albedo, fuzz } => {
+                Arc::new(Metal::with_texture(texture(albedo)?, *fuzz))
+            }
+            MaterialDesc::Dielectric { ir } => Arc::new(Dielectric::new(*ir)),
+            MaterialDesc::DiffuseLight { emit } => {
+                Arc::new(DiffuseLight::with_texture(texture(emit)?))
+            }
+        };
+        materials.insert(name, mat);
+    }
+
+    let mut world = HittableList::new();
+    for obj in &desc.objects {
+        let material = |name: &String| {
+            materials
+                .get(name.as_str())
+                .cloned()
+                .ok_or_else(|| source.error(obj.span(), format!("unknown material `{name}`")))
+        };
+
+        let (object, motion, interpolation): (Box<dyn Hittable>, _, _) = match obj.get_ref() {
+            ObjectDesc::Sphere {
+                centre,
+                radius,
+                material: mat,
+                motion,
+                interpolation,
+            } => (
+                Box::new(Sphere::new(vec3(centre), *radius, material(mat)?)),
+                motion,
+                interpolation,
+            ),
+            ObjectDesc::MovingSphere {
+                keyframes,
+                radius,
+                material: mat,
+                interpolation,
+            } => {
+                if keyframes.is_empty() {
+                    return Err(source.error(obj.span(), "moving sphere without keyframes"));
+                }
+                let path = keyframes
+                    .iter()
+                    .map(|k| (k.time, vec3(&k.centre)))
+                    .collect();
+                (
+                    Box::new(MovingSphere::keyframed(
+                        Keyframes::new(path).with_interpolation(*interpolation),
+                        *radius,
+                        material(mat)?,
+                    )),
+                    &Vec::new(),
+                    interpolation,
+                )
+            }
+            ObjectDesc::Triangle {
+                vertices: [v0, v1, v2],
+                material: mat,
+                motion,
+                interpolation,
+            } => (
+                Box::new(Triangle::new(vec3(v0), vec3(v1), vec3(v2), material(mat)?)),
+                motion,
+                interpolation,
+            ),
+            ObjectDesc::Obj {
+                path,
+                motion,
+                interpolation,
+            } => (
+                Box::new(load_obj_in(base_dir.join(path), settings.colour_space)?),
+                motion,
+                interpolation,
+            ),
+        };
+
+        if motion.is_empty() {
+            world.objects.push(object);
+            continue;
+        }
+        if motion.iter().any(|k| k.scale <= 0.) {
+            return Err(source.error(obj.span(), "motion scale must be positive"));
+        }
+        let keys = motion
+            .iter()
+            .map(|k| {
+                let transform = Transform {
+                    translate: vec3(&k.translate),
+                    rotate: vec3(&k.rotate),
+                    scale: k.scale,
+                };
+                (k.time, transform)
+            })
+            .collect();
+        world.add(Transformed::animated(
+            object,
+            Keyframes::new(keys).with_interpolation(*interpolation),
+        ));
+    }
+
+    let background = match desc.background {
+        None => Background::default(),
+        Some(BackgroundDesc::None) => Background::None,
+        Some(BackgroundDesc::Solid { colour }) => Background::Solid(vec3(&colour)),
+        Some(BackgroundDesc::Gradient { bottom, top }) => Background::Gradient {
+            bottom: vec3(&bottom),
+            top: vec3(&top),
+        },
+    }
+    .in_colour_space(settings.colour_space);
+
+    let focus_pixel = match &desc.camera.autofocus {
+        Some(px) => {
+            let [x, y] = source.check(
+                px,
+                |[x, y]| (x as i32) < settings.width && (y as i32) < settings.height,
+                "autofocus pixel must lie inside the image",
+            )?;
+            Some((x, y))
+        }
+        None => None,
+    };
+
+    let stereo = match &desc.camera.stereo {
+        Some(stereo) => {
+            source.check(
+                stereo,
+                |s| s.interocular >= 0. && s.convergence > 0.,
+                "interocular must not be negative and convergence must be positive",
+            )?;
+            Some(source.check(
+                stereo,
+                |s| s.fits(settings.width, settings.height),
+                "image is too small to split into a stereo pair",
+            )?)
+        }
+        None => None,
+    };
+
+    let animation = match &desc.animation {
+        Some(anim) => Some(animation(&source, anim, &desc.camera, &camera)?),
+        None => None,
+    };
+
+    let tone_mapping = match &desc.tone_mapping {
+        Some(tm) => source.check(
+            tm,
+            |tm| tm.white.is_none_or(|w| w > 0.),
+            "white must be positive",
+        )?,
+        None => ToneMapping::default(),
+    };
+
+    Ok(Scene {
+        world,
+        camera,
+        settings,
+        background,
+        focus_pixel,
+        stereo,
+        animation,
+        exposure: desc.camera.exposure.map(|e| e.into_inner()),
+        tone_mapping,
+    })
+}
+
+fn animation(
+    source: &Source,
+    desc: &AnimationDesc,
+    camera_desc: &CameraDesc,
+    camera: &Camera,
+) -> Result<Animation, SceneError> {
+    let frames = source.check(&desc.frames, |f| f > 0, "frames must be positive")?;
+    let fps = match &desc.fps {
+        Some(fps) => source.check(fps, |f| f > 0., "fps must be positive")?,
+        None => 24.,
+    };
+
+    for key in &desc.camera {
+        let k = key.get_ref();
+        if k.vfov.is_some_and(|v| v <= 0. || v >= 180.) {
+            return Err(source.error(key.span(), "vfov must be between 0 and 180 degrees"));
+        }
+        if k.focus_dist.is_some_and(|d| d <= 0.) {
+            return Err(source.error(key.span(), "focus_dist must be positive"));
+        }
+    }
+
+    // Properties without keys hold their `[camera]` value.
+    let track = |value: fn(&CameraKeyDesc) -> Option<f64>, still: f64| {
+        let keys: Vec<_> = desc
+            .camera
+            .iter()
+            .filter_map(|k| Some((k.get_ref().time, value(k.get_ref())?)))
+            .collect();
+        keyframes(keys, still, desc.interpolation)
+    };
+    let point_track = |value: fn(&CameraKeyDesc) -> Option<[f64; 3]>, still: &[f64; 3]| {
+        let keys: Vec<_> = desc
+            .camera
+            .iter()
+            .filter_map(|k| Some((k.get_ref().time, vec3(&value(k.get_ref())?))))
+            .collect();
+        keyframes(keys, vec3(still), desc.interpolation)
+    };
+
+    let lookfrom = point_track(|k| k.lookfrom, &camera_desc.lookfrom);
+    let lookat = point_track(|k| k.lookat, &camera_desc.lookat);
+    let vfov = track(|k| k.vfov, *camera_desc.vfov.get_ref());
+
+    // Without a focus distance anywhere, keep focusing on `lookat`.
+    let keyed_focus = desc.camera.iter().any(|k| k.get_ref().focus_dist.is_some());
+    let focus_dist = if keyed_focus || camera_desc.focus_dist.is_some() {
+        track(|k| k.focus_dist, camera.focus_dist())
+    } else {
+        let mut times: Vec<f64> = lookfrom
+            .keys()
+            .iter()
+            .chain(lookat.keys())
+            .map(|k| k.0)
+            .collect();
+        times.sort_by(f64::total_cmp);
+        times.dedup();
+        let keys = times
+            .into_iter()
+            .map(|t| (t, (lookfrom.at(t) - lookat.at(t)).len()))
+            .collect();
+        Keyframes::new(keys).with_interpolation(desc.interpolation)
+    };
+
+    let path = CameraPath {
+        lookfrom,
+        lookat,
+        vup: vec3(&camera_desc.vup),
+        vfov,
+        focus_dist,
+        exposure: camera_desc.exposure.as_ref().map(|e| *e.get_ref()),
+    };
+
+    Ok(Animation {
+        frames,
+        fps,
+        start: desc.start,
+        camera: (!desc.camera.is_empty()).then_some(path),
+        fixed_seed: desc.fixed_seed,
+    })
+}
+
+fn keyframes<T: Lerp>(keys: Vec<(f64, T)>, still: T, interpolation: Interpolation) -> Keyframes<T> {
+    if keys.is_empty() {
+        Keyframes::constant(still)
+    } else {
+        Keyframes::new(keys).with_interpolation(interpolation)
+    }
+}
+
+fn render_settings(source: &Source, desc: &RenderDesc) -> Result<RenderSettings, SceneError> {
+    let mut settings = RenderSettings::default();
+
+    if let Some(width) = &desc.width {
+        settings.width = source.check(width, |w| w > 0, "width must be positive")?;
+    }
+
+    match (&desc.height, &desc.aspect_ratio) {
+        (Some(_), Some(ratio)) => {
+            return Err(source.error(
+                ratio.span(),
+                "only one of height and aspect_ratio may be set",
+            ))
+        }
+        (Some(height), None) => {
+            settings.height = source.check(height, |h| h > 0, "height must be positive")?;
+        }
+        (None, ratio) => {
+            let ratio = match ratio {
+                Some(ratio) => source.check(ratio, |r| r > 0., "aspect_ratio must be positive")?,
+                None => DEFAULT_ASPECT_RATIO,
+            };
+            settings.height = ((settings.width as f64 / ratio) as i32).max(1);
+        }
+    }
+
+    if let Some(spp) = &desc.samples_per_pixel {
+        settings.samples_per_pixel =
+            source.check(spp, |s| s > 0, "samples_per_pixel must be positive")?;
+    }
+    if let Some(depth) = &desc.max_depth {
+        settings.max_depth = source.check(depth, |d| d > 0, "max_depth must be positive")?;
+    }
+    if let Some(adaptive) = &desc.adaptive {
+        let spp = settings.samples_per_pixel;
+        source.check(adaptive, |a| a.threshold > 0., "threshold must be positive")?;
+        source.check(
+            adaptive,
+            |a| a.max_samples_per_pixel.is_none_or(|m| m >= spp),
+            "max_samples_per_pixel must be at least samples_per_pixel",
+        )?;
+        settings.adaptive = Some(source.check(
+            adaptive,
+            |a| a.samples_per_pass.is_none_or(|s| s > 0),
+            "samples_per_pass must be positive",
+        )?);
+    }
+    if let Some(output) = &desc.output {
+        settings.output = output.clone();
+    }
+    settings.sample_map = desc.sample_map.clone();
+    settings.seed = desc.seed;
+    settings.sampler = desc.sampler;
+    if let Some(filter) = &desc.filter {
+        settings.filter = source.check(
+            filter,
+            |f| f.radius.is_none_or(|r| r > 0.),
+            "filter radius must be positive",
+        )?;
+    }
+    settings.colour_space = desc.colour_space;
+    settings.bit_depth = desc.bit_depth;
+    settings.precision = desc.precision;
+    settings.display_layer = desc.display_layer;
+
+    Ok(settings)
+}
+
+fn camera(
+    source: &Source,
+    desc: &CameraDesc,
+    aspect_ratio: f64,
+    base_dir: &Path,
+) -> Result<Camera, SceneError> {
+    let from = vec3(&desc.lookfrom);
+    let to = vec3(&desc.lookat);
+
+    let vfov = source.check(
+        &desc.vfov,
+        |v| v > 0. && v < 180.,
+        "vfov must be between 0 and 180 degrees",
+    )?;
+    let exposure = match &desc.exposure {
+        Some(e) => Some(source.check(
+            e,
+            |e| {
+                e.f_number > 0.
+                    && e.shutter_speed > 0.
+                    && e.iso > 0.
+                    && e.sensor_height > 0.
+                    && (1667. ..=25000.).contains(&e.white_balance)
+            },
+            "exposure settings must be positive and white_balance between 1667 and 25000 K",
+        )?),
+        None => None,
+    };
+    let aperture = match (&desc.aperture, exposure) {
+        (Some(a), None) => source.check(a, |a| a >= 0., "aperture must not be negative")?,
+        (Some(a), Some(_)) => {
+            return Err(source.error(a.span(), "aperture is set by the exposure's f_number"))
+        }
+        (None, Some(e)) => e.aperture(vfov),
+        (None, None) => 0.,
+    };
+    let focus_dist = match &desc.focus_dist {
+        Some(d) => source.check(d, |d| d > 0., "focus_dist must be positive")?,
+        None => (from - to).len(),
+    };
+
+    let shape = match &desc.bokeh {
+        Some(bokeh) => match bokeh.get_ref() {
+            BokehDesc::Circle => ApertureShape::Circle,
+            BokehDesc::Polygon {
+                blades,
+                rotation,
+                rounding,
+            } => {
+                if *blades < 3 {
+                    return Err(source.error(bokeh.span(), "bokeh needs at least 3 blades"));
+                }
+                if !(0. ..=1.).contains(rounding) {
+                    return Err(source.error(bokeh.span(), "rounding must be between 0 and 1"));
+                }
+                ApertureShape::Polygon {
+                    blades: *blades,
+                    rotation: *rotation,
+                    rounding: *rounding,
+                }
+            }
+            BokehDesc::Mask { path } => {
+                let image = ImageTexture::load(base_dir.join(path))?;
+                let mask = ApertureMask::new(&image)
+                    .ok_or_else(|| source.error(bokeh.span(), "aperture mask is black"))?;
+                ApertureShape::Mask(Arc::new(mask))
+            }
+        },
+        None => ApertureShape::Circle,
+    };
+    let squeeze = match &desc.anamorphic_squeeze {
+        Some(s) => source.check(s, |s| s > 0., "anamorphic_squeeze must be positive")?,
+        None => 1.,
+    };
+    let vignetting = match &desc.vignetting {
+        Some(v) => source.check(v, |v| v >= 0., "vignetting must not be negative")?,
+        None => 0.,
+    };
+
+    let shutter = match &desc.shutter {
+        Some(s) if exposure.is_some() => {
+            return Err(source.error(s.span(), "shutter is set by the exposure's shutter_speed"))
+        }
+        Some(s) => {
+            let [open, close] = source.check(
+                s,
+                |[open, close]| open <= close,
+                "shutter closes before it opens",
+            )?;
+            (open, close)
+        }
+        None => (0., exposure.map_or(0., |e| e.shutter_speed)),
+    };
+    let projection = match &desc.projection {
+        Some(p) => source.check(
+            p,
+            |p| match p {
+                Projection::Orthographic { height } => height > 0.,
+                Projection::Fisheye { fov, .. } => fov > 0. && fov <= 360.,
+                _ => true,
+            },
+            "orthographic height must be positive and fisheye fov between 0 and 360 degrees",
+        )?,
+        None => Projection::Perspective,
+    };
+
+    Ok(Camera::new(
+        &from,
+        &to,
+        &vec3(&desc.vup),
+        vfov,
+        aspect_ratio,
+        aperture,
+        focus_dist,
+    )
+    .with_shutter(shutter.0, shutter.1)
+    .with_projection(projection)
+    .with_aperture_shape(shape)
+    .with_anamorphic_squeeze(squeeze)
+    .with_vignetting(vignetting))
+}
+
+/// Loads a scene description from a TOML file.
+pub fn load_scene(path: impl AsRef<Path>) -> Result<Scene, SceneError> {
+    let path = path.as_ref();
+    let src = fs::read_to_string(path).map_err(|source| SceneError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    parse_scene(
+        &src,
+        &path.display().to_string(),
+        path.parent().unwrap_or(Path::new("")),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        camera::StereoLayout, filter::FilterKind, hittable::Hittable, ray::Ray,
+        tonemap::ToneMapper, vec3::Point,
+    };
+
+    const SCENE: &str = r#"
+[camera]
+lookfrom = [0, 0, 5]
+lookat = [0, 0, 0]
+vfov = 40
+
+[render]
+width = 64
+aspect_ratio = 2.0
+samples_per_pixel = 4
+max_depth = 8
+
+[materials.red]
+type = "lambertian"
+albedo = [0.8, 0.1, 0.1]
+
+[materials.glass]
+type = "dielectric"
+ir = 1.5
+
+[[objects]]
+type = "sphere"
+centre = [0, 0, 0]
+radius = 1
+material = "red"
+
+[[objects]]
+type = "sphere"
+centre = [3, 0, 0]
+radius = 0.5
+material = "glass"
+
+[[objects]]
+type = "triangle"
+vertices = [[-3, -1, 0], [-2, -1, 0], [-2, 0, 0]]
+material = "red"
+"#;
+
+    fn parse(src: &str) -> Result<Scene, SceneError> {
+        parse_scene(src, "test.toml", Path::new(""))
+    }
+
+    fn error(src: &str) -> String {
+        parse(src).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn parses_scene() {
+        let scene = parse(SCENE).unwrap();
+
+        assert_eq!(scene.settings.width, 64);
+        assert_eq!(scene.settings.height, 32);
+        assert_eq!(scene.settings.samples_per_pixel, 4);
+        assert_eq!(scene.settings.max_depth, 8);
+        assert_eq!(scene.world.objects.len(), 3);
+
+        let ray = Ray::new(&Point::new(0., 0., 5.), &Vec3::new(0., 0., -1.));
+        let rec = scene.world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 4.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn defaults() {
+        let scene =
+            parse("[camera]\nlookfrom = [0, 0, 1]\nlookat = [0, 0, 0]\nvfov = 90\n").unwrap();
+        let defaults = RenderSettings::default();
+
+        assert_eq!(scene.settings.width, defaults.width);
+        assert_eq!(scene.settings.height, defaults.height);
+        assert!(scene.world.objects.is_empty());
+    }
+
+    #[test]
+    fn autofocus() {
+        assert_eq!(parse(SCENE).unwrap().focus_pixel, None);
+
+        let src = SCENE.replace("vfov = 40", "vfov = 40\nautofocus = [10, 20]");
+        assert_eq!(parse(&src).unwrap().focus_pixel, Some((10, 20)));
+
+        let err = error(&SCENE.replace("vfov = 40", "vfov = 40\nautofocus = [64, 0]"));
+        assert_eq!(
+            err,
+            "test.toml:6:13: autofocus pixel must lie inside the image"
+        );
+    }
+
+    #[test]
+    fn bokeh() {
+        let lens = "vfov = 40\nanamorphic_squeeze = 1.33\nvignetting = 0.5\n\n\
+                    [camera.bokeh]\ntype = \"polygon\"\nblades = 6\nrotation = 30\n";
+        assert!(parse(&SCENE.replace("vfov = 40", lens)).is_ok());
+
+        let err = error(&SCENE.replace("vfov = 40", &lens.replace("6", "2")));
+        assert!(err.ends_with("bokeh needs at least 3 blades"), "{err}");
+
+        let err = error(&SCENE.replace("vfov = 40", &lens.replace("1.33", "0")));
+        assert_eq!(err, "test.toml:6:22: anamorphic_squeeze must be positive");
+    }
+
+    #[test]
+    fn projection() {
+        let src = SCENE.replace(
+            "vfov = 40",
+            "vfov = 40\n\n[camera.projection]\ntype = \"fisheye\"\nfov = 180\nmapping = \"equisolid\"\n",
+        );
+        assert!(parse(&src).is_ok());
+
+        let err = error(&src.replace("fov = 180", "fov = 400"));
+        assert!(err.starts_with("test.toml:7:1:"), "{err}");
+        assert!(err.contains("fisheye fov"), "{err}");
+
+        let err = error(&src.replace("\"fisheye\"", "\"cylindrical\""));
+        assert!(err.contains("unknown variant `cylindrical`"), "{err}");
+    }
+
+    #[test]
+    fn stereo() {
+        assert_eq!(parse(SCENE).unwrap().stereo, None);
+
+        let src = SCENE.replace(
+            "vfov = 40",
+            "vfov = 40\n\n[camera.stereo]\nlayout = \"over_under\"\ninterocular = 0.064\n",
+        );
+        let stereo = parse(&src).unwrap().stereo.unwrap();
+        assert_eq!(stereo.layout, StereoLayout::OverUnder);
+        assert_eq!(stereo.convergence, f64::INFINITY);
+
+        let err = error(&src.replace("0.064", "0.064\nconvergence = 0"));
+        assert!(err.starts_with("test.toml:7:1:"), "{err}");
+
+        let err = error(&src.replace("aspect_ratio = 2.0", "height = 1"));
+        assert!(err.contains("too small to split"), "{err}");
+    }
+
+    #[test]
+    fn exposure() {
+        assert_eq!(parse(SCENE).unwrap().exposure, None);
+
+        let src = SCENE.replace(
+            "vfov = 40",
+            "vfov = 40\n\n[camera.exposure]\nf_number = 2.8\nshutter_speed = 0.02\niso = 400\n",
+        );
+        let scene = parse(&src).unwrap();
+        let exposure = scene.exposure.unwrap();
+        assert_eq!((exposure.iso, exposure.white_balance), (400., 6504.));
+        assert_eq!(scene.camera.shutter(), (0., 0.02));
+
+        let err = error(&src.replace("vfov = 40", "vfov = 40\naperture = 0.1"));
+        assert_eq!(
+            err,
+            "test.toml:6:12: aperture is set by the exposure's f_number"
+        );
+        let err = error(&src.replace("vfov = 40", "vfov = 40\nshutter = [0, 1]"));
+        assert!(
+            err.ends_with("shutter is set by the exposure's shutter_speed"),
+            "{err}"
+        );
+        let err = error(&src.replace("iso = 400", "iso = 400\nwhite_balance = 500"));
+        assert!(err.starts_with("test.toml:7:1:"), "{err}");
+    }
+
+    #[test]
+    fn motion() {
+        let src = SCENE.replace("vfov = 40", "vfov = 40\nshutter = [0, 1]")
+            + "\n[[objects]]\ntype = \"moving_sphere\"\nradius = 0.5\nmaterial = \"red\"\n\
+               keyframes = [{ time = 0, centre = [0, 0, 0] }, { time = 1, centre = [0, 1, 0] }]\n\n\
+               [[objects]]\ntype = \"sphere\"\ncentre = [0, 0, 0]\nradius = 1\nmaterial = \"red\"\n\
+               motion = [{ time = 0 }, { time = 1, translate = [2, 0, 0], rotate = [0, 90, 0] }]\n";
+        let scene = parse(&src).unwrap();
+        assert_eq!(scene.world.objects.len(), 5);
+
+        let bbox = scene.world.objects[4].bounding_box().unwrap();
+        assert!(bbox.min.x <= -1. && bbox.max.x >= 3., "{:?}", bbox);
+
+        let err = error(&src.replace("shutter = [0, 1]", "shutter = [1, 0]"));
+        assert_eq!(err, "test.toml:6:11: shutter closes before it opens");
+
+        let err = error(&src.replace("translate = [2, 0, 0]", "scale = 0"));
+        assert!(err.ends_with("motion scale must be positive"), "{err}");
+    }
+
+    #[test]
+    fn animation() {
+        assert!(parse(SCENE).unwrap().animation.is_none());
+
+        let src = format!(
+            "{SCENE}\n[animation]\nframes = 48\ninterpolation = \"bezier\"\nfixed_seed = true\n\
+             camera = [{{ time = 0, lookfrom = [0, 0, 5] }}, {{ time = 2, lookfrom = [5, 0, 0], vfov = 60 }}]\n"
+        );
+        let scene = parse(&src).unwrap();
+        let anim = scene.animation.unwrap();
+        assert_eq!((anim.frames, anim.fps, anim.fixed_seed), (48, 24., true));
+
+        // Unkeyed properties hold still and the focus follows `lookat`.
+        let path = anim.camera.unwrap();
+        let from = path.lookfrom.at(2.);
+        assert_eq!((from.x, from.y, from.z), (5., 0., 0.));
+        assert_eq!(path.vfov.at(0.), 60.);
+        assert_eq!(path.focus_dist.at(1.), 5.);
+        assert!(path.lookat.at(1.).len() < 1e-12);
+
+        let err = error(&src.replace("vfov = 60", "vfov = 180"));
+        assert!(
+            err.ends_with("vfov must be between 0 and 180 degrees"),
+            "{err}"
+        );
+        let err = error(&src.replace("frames = 48", "frames = 0"));
+        assert!(err.ends_with("frames must be positive"), "{err}");
+    }
+
+    #[test]
+    fn background() {
+        assert!(matches!(
+            parse(SCENE).unwrap().background,
+            Background::Gradient { .. }
+        ));
+
+        let src = format!("{SCENE}\n[background]\ntype = \"none\"\n");
+        assert!(matches!(parse(&src).unwrap().background, Background::None));
+
+        let src = format!("{SCENE}\n[background]\ntype = \"solid\"\ncolour = [0.1, 0.2, 0.3]\n");
+        match parse(&src).unwrap().background {
+            Background::Solid(c) => assert_eq!((c.x, c.y, c.z), (0.1, 0.2, 0.3)),
+            _ => panic!("expected a solid background"),
+        }
+    }
+
+    #[test]
+    fn colour_space() {
+        assert_eq!(
+            parse(SCENE).unwrap().settings.colour_space,
+            ColourSpace::Srgb
+        );
+
+        let src = SCENE.replace("max_depth = 8", "max_depth = 8\ncolour_space = \"aces_cg\"")
+            + "\n[background]\ntype = \"solid\"\ncolour = [1, 0, 0]\n";
+        let scene = parse(&src).unwrap();
+        assert_eq!(scene.settings.colour_space, ColourSpace::AcesCg);
+        match scene.background {
+            Background::Solid(c) => assert!((c.x - 0.613).abs() < 1e-3, "{c}"),
+            _ => panic!("expected a solid background"),
+        }
+
+        let err = error(&src.replace("aces_cg", "p3"));
+        assert!(err.contains("unknown variant `p3`"), "{err}");
+    }
+
+    #[test]
+    fn sampler() {
+        assert_eq!(parse(SCENE).unwrap().settings.sampler, SamplerKind::Random);
+
+        let src = SCENE.replace("max_depth = 8", "max_depth = 8\nsampler = \"blue_noise\"");
+        assert_eq!(
+            parse(&src).unwrap().settings.sampler,
+            SamplerKind::BlueNoise
+        );
+        let err = error(&src.replace("blue_noise", "latin"));
+        assert!(err.contains("unknown variant `latin`"), "{err}");
+    }
+
+    #[test]
+    fn adaptive_sampling() {
+        let settings = parse(SCENE).unwrap().settings;
+        assert_eq!((settings.adaptive, settings.sample_map), (None, None));
+
+        let src = SCENE.replace(
+            "max_depth = 8",
+            "max_depth = 8\nadaptive = { threshold = 0.05, max_samples_per_pixel = 64 }\n\
+             sample_map = \"samples.exr\"",
+        );
+        let settings = parse(&src).unwrap().settings;
+        assert_eq!(
+            settings.adaptive,
+            Some(AdaptiveSampling::new(0.05).with_max_samples(64))
+        );
+        assert_eq!(settings.sample_map, Some(PathBuf::from("samples.exr")));
+
+        let err = error(&src.replace("0.05", "0"));
+        assert!(err.contains("threshold must be positive"), "{err}");
+        let err = error(&src.replace("64", "2"));
+        assert!(err.contains("at least samples_per_pixel"), "{err}");
+        let err = error(&src.replace("}", ", samples_per_pass = 0 }"));
+        assert!(err.contains("samples_per_pass must be positive"), "{err}");
+        let err = error(&src.replace("threshold", "tolerance"));
+        assert!(err.contains("unknown field `tolerance`"), "{err}");
+    }
+
+    #[test]
+    fn filter() {
+        let settings = parse(SCENE).unwrap().settings;
+        assert_eq!(settings.filter, PixelFilter::default());
+        assert_eq!(settings.filter.radius(), 0.5);
+
+        let src = SCENE.replace(
+            "max_depth = 8",
+            "max_depth = 8\nfilter = { type = \"lanczos\", radius = 2 }",
+        );
+        let filter = parse(&src).unwrap().settings.filter;
+        assert_eq!(
+            filter,
+            PixelFilter::new(FilterKind::Lanczos).with_radius(2.)
+        );
+
+        let src = src.replace(", radius = 2", "");
+        assert_eq!(parse(&src).unwrap().settings.filter.radius(), 3.);
+
+        let err = error(&src.replace(" }", ", radius = -1 }"));
+        assert!(err.contains("filter radius must be positive"), "{err}");
+        let err = error(&src.replace("lanczos", "sinc"));
+        assert!(err.contains("unknown variant `sinc`"), "{err}");
+    }
+
+    #[test]
+    fn float_output() {
+        let settings = parse(SCENE).unwrap().settings;
+        assert_eq!(settings.precision, Precision::Half);
+        assert!(!settings.display_layer);
+
+        let src = SCENE.replace(
+            "max_depth = 8",
+            "max_depth = 8\nprecision = \"float\"\ndisplay_layer = true",
+        );
+        let settings = parse(&src).unwrap().settings;
+        assert_eq!(settings.precision, Precision::Float);
+        assert!(settings.display_layer);
+    }
+
+    #[test]
+    fn tone_mapping() {
+        assert_eq!(parse(SCENE).unwrap().tone_mapping, ToneMapping::default());
+
+        let src =
+            format!("{SCENE}\n[tone_mapping]\noperator = \"hable\"\nexposure = 1\nwhite = 8\n");
+        let tm = parse(&src).unwrap().tone_mapping;
+        assert_eq!(
+            tm,
+            ToneMapping::new(ToneMapper::Hable)
+                .with_exposure(1.)
+                .with_white(8.)
+        );
+
+        let err = error(&src.replace("white = 8", "white = 0"));
+        assert!(err.ends_with("white must be positive"), "{err}");
+        let err = error(&src.replace("hable", "filmic"));
+        assert!(err.contains("unknown variant `filmic`"), "{err}");
+    }
+
+    #[test]
+    fn textured_material() {
+        let src = SCENE.replace("albedo = [0.8, 0.1, 0.1]", "albedo = \"checks\"")
+            + "\n[textures.checks]\ntype = \"checker\"\nscale = 1.0\neven = [1, 1, 1]\nodd = [0, 0, 0]\n";
+        assert!(parse(&src).is_ok());
+
+        let err = error(&SCENE.replace("albedo = [0.8, 0.1, 0.1]", "albedo = \"checks\""));
+        assert_eq!(err, "test.toml:13:1: unknown texture `checks`");
+    }
+
+    #[test]
+    fn syntax_error_has_line() {
+        let err = error("[camera]\nlookfrom = [0, 0, 1\n");
+        assert_eq!(err, "test.toml:3:1: invalid array: expected `]`");
+    }
+
+    #[test]
+    fn missing_field() {
+        let err = error("[camera]\nlookfrom = [0, 0, 1]\nlookat = [0, 0, 0]\n");
+        assert!(err.starts_with("test.toml:1:1:"), "{err}");
+        assert!(err.contains("missing field `vfov`"), "{err}");
+    }
+
+    #[test]
+    fn unknown_field() {
+        let err = error(&SCENE.replace("max_depth = 8", "max_dpeth = 8"));
+        assert!(err.starts_with("test.toml:11:1:"), "{err}");
+        assert!(err.contains("unknown field `max_dpeth`"), "{err}");
+    }
+
+    #[test]
+    fn invalid_value() {
+        let err = error(&SCENE.replace("width = 64", "width = -64"));
+        assert_eq!(err, "test.toml:8:9: width must be positive");
+
+        let err = error(&SCENE.replace("vfov = 40", "vfov = 180"));
+        assert_eq!(err, "test.toml:5:8: vfov must be between 0 and 180 degrees");
+    }
+
+    #[test]
+    fn unknown_material() {
+        let err = error(&SCENE.replace("material = \"glass\"", "material = \"gold\""));
+        assert_eq!(err, "test.toml:27:1: unknown material `gold`");
+    }
+
+    #[test]
+    fn unknown_object_type() {
+        let err = error(&SCENE.replace("type = \"triangle\"", "type = \"cube\""));
+        assert!(err.starts_with("test.toml:34:8:"), "{err}");
+        assert!(err.contains("unknown variant `cube`"), "{err}");
+    }
+
+    #[test]
+    fn image_texture() {
+        let dir = tempfile::tempdir().unwrap();
+        image::RgbImage::from_pixel(2, 2, image::Rgb([0, 255, 0]))
+            .save(dir.path().join("green.png"))
+            .unwrap();
+
+        let src = SCENE.replace("albedo = [0.8, 0.1, 0.1]", "albedo = \"grass\"")
+            + "\n[textures.grass]\ntype = \"image\"\npath = \"green.png\"\nwrap = \"clamp\"\n";
+        assert!(parse_scene(&src, "test.toml", dir.path()).is_ok());
+
+        let src = src.replace("green.png", "missing.png");
+        assert!(matches!(
+            parse_scene(&src, "test.toml", dir.path()),
+            Err(SceneError::Texture(TextureError::Io { .. }))
+        ));
+    }
+
+    #[test]
+    fn noise_texture() {
+        let src = SCENE.replace("albedo = [0.8, 0.1, 0.1]", "albedo = \"marble\"")
+            + "\n[textures.marble]\ntype = \"noise\"\npattern = \"marble\"\nscale = 4\nseed = 7\n";
+        assert!(parse(&src).is_ok());
+
+        let err = error(&src.replace("pattern = \"marble\"", "pattern = \"granite\""));
+        assert!(err.contains("unknown variant `granite`"), "{err}");
+    }
+
+    #[test]
+    fn missing_obj_file() {
+        let src = format!("{SCENE}\n[[objects]]\ntype = \"obj\"\npath = \"nope.obj\"\n");
+        assert!(matches!(
+            parse(&src),
+            Err(SceneError::Obj(ObjError::Io { .. }))
+        ));
+    }
+}
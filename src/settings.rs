@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+
+use crate::{
+    adaptive::AdaptiveSampling,
+    colour::ColourSpace,
+    filter::PixelFilter,
+    output::{BitDepth, Precision},
+    sampler::SamplerKind,
+};
+
+pub const DEFAULT_ASPECT_RATIO: f64 = 3. / 2.;
+
+#[derive(Clone, Debug)]
+pub struct RenderSettings {
+    pub width: i32,
+    pub height: i32,
+    pub samples_per_pixel: i64,
+    pub max_depth: i32,
+    /// Keeps sampling noisy pixels past `samples_per_pixel` when set.
+    pub adaptive: Option<AdaptiveSampling>,
+    pub output: PathBuf,
+    /// Where to write an image of the samples each pixel took.
+    pub sample_map: Option<PathBuf>,
+    /// Makes renders reproducible. Fresh random numbers every run when `None`.
+    pub seed: Option<u64>,
+    /// Where pixel, lens and bounce samples come from.
+    pub sampler: SamplerKind,
+    /// How samples are weighted into the pixels around them.
+    pub filter: PixelFilter,
+    /// Linear space that light is traced in.
+    pub colour_space: ColourSpace,
+    /// Bits per channel of the output image.
+    pub bit_depth: BitDepth,
+    /// Sample type of OpenEXR output.
+    pub precision: Precision,
+    /// Adds the tone mapped image to OpenEXR output as a `display` layer.
+    pub display_layer: bool,
+}
+
+impl RenderSettings {
+    pub fn aspect_ratio(&self) -> f64 {
+        self.width as f64 / self.height as f64
+    }
+
+    /// Camera coordinates of the centre of pixel `(x, y)`, counting rows from
+    /// the top of the image. The pixel must lie inside the image.
+    pub fn pixel_uv(&self, x: u32, y: u32) -> (f64, f64) {
+        let (width, height) = (self.width as f64, self.height as f64);
+        ((x as f64 + 0.5) / width, (height - y as f64 - 0.5) / height)
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        let width = 1200;
+        Self {
+            width,
+            height: (width as f64 / DEFAULT_ASPECT_RATIO) as i32,
+            samples_per_pixel: 500,
+            max_depth: 50,
+            adaptive: None,
+            output: PathBuf::from("img.png"),
+            sample_map: None,
+            seed: None,
+            sampler: SamplerKind::default(),
+            filter: PixelFilter::default(),
+            colour_space: ColourSpace::default(),
+            bit_depth: BitDepth::default(),
+            precision: Precision::default(),
+            display_layer: false,
+        }
+    }
+}
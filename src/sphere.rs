@@ -1,10 +1,12 @@
-use std::sync::Arc;
+use std::{f64::consts::PI, sync::Arc};
 
 use crate::{
+    aabb::Aabb,
     hittable::{HitRecord, Hittable},
     material::Material,
+    motion::Keyframes,
     ray::Ray,
-    vec3::Point,
+    vec3::{Point, Vec3},
 };
 
 pub struct Sphere {
@@ -21,37 +23,191 @@ impl Sphere {
             mat: mat.clone(),
         }
     }
+
+    /// Spherical coordinates of a point on the unit sphere: `u` goes around
+    /// the y axis starting from -x, `v` from the bottom pole (0) to the top (1).
+    fn uv(p: &Point) -> (f64, f64) {
+        let theta = (-p.y).clamp(-1., 1.).acos();
+        let phi = (-p.z).atan2(p.x) + PI;
+
+        (phi / (2. * PI), theta / PI)
+    }
 }
 
 impl Hittable for Sphere {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let oc = ray.origin() - self.centre;
-        let a = ray.dir().len_squared();
-        let half_b = oc.dot(&ray.dir());
-        let c = oc.len_squared() - self.r.powi(2);
+        hit_sphere(&self.centre, self.r, &self.mat, ray, t_min, t_max)
+    }
 
-        let delta_sq = half_b.powi(2) - a * c;
-        if delta_sq < 0. {
-            return None;
-        }
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(sphere_box(&self.centre, self.r))
+    }
+}
+
+/// Sphere whose centre moves along keyframes, blurring during an exposure.
+pub struct MovingSphere {
+    centre: Keyframes<Point>,
+    r: f64,
+    mat: Arc<dyn Material>,
+}
 
-        let delta = delta_sq.sqrt();
+impl MovingSphere {
+    /// Moves in a straight line from `centre0` at `time0` to `centre1` at `time1`.
+    pub fn new(
+        centre0: Point,
+        time0: f64,
+        centre1: Point,
+        time1: f64,
+        r: f64,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        Self::keyframed(
+            Keyframes::new(vec![(time0, centre0), (time1, centre1)]),
+            r,
+            mat,
+        )
+    }
+
+    pub fn keyframed(centre: Keyframes<Point>, r: f64, mat: Arc<dyn Material>) -> Self {
+        Self { centre, r, mat }
+    }
+}
+
+impl Hittable for MovingSphere {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let centre = self.centre.at(ray.time());
+        hit_sphere(&centre, self.r, &self.mat, ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.centre
+            .hull()
+            .iter()
+            .map(|c| sphere_box(c, self.r))
+            .reduce(|a, b| a.union(&b))
+    }
+}
 
-        let mut root = (-half_b - delta) / a;
+fn sphere_box(centre: &Point, r: f64) -> Aabb {
+    let r = Vec3::new(r, r, r);
+    Aabb::new(&(*centre - r), &(*centre + r))
+}
 
+fn hit_sphere(
+    centre: &Point,
+    r: f64,
+    mat: &Arc<dyn Material>,
+    ray: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<HitRecord> {
+    let oc = ray.origin() - *centre;
+    let a = ray.dir().len_squared();
+    let half_b = oc.dot(&ray.dir());
+    let c = oc.len_squared() - r.powi(2);
+
+    let delta_sq = half_b.powi(2) - a * c;
+    if delta_sq < 0. {
+        return None;
+    }
+
+    let delta = delta_sq.sqrt();
+
+    let mut root = (-half_b - delta) / a;
+
+    if root < t_min || root > t_max {
+        root = (-half_b + delta) / a;
         if root < t_min || root > t_max {
-            root = (-half_b + delta) / a;
-            if root < t_min || root > t_max {
-                return None;
-            }
+            return None;
         }
+    }
+
+    let mut record = HitRecord::new(mat);
+    record.t = root;
+    record.p = ray.at(root);
+    let outward_n = (record.p - *centre) / r;
+    record.set_face_normal(ray, outward_n);
+    (record.u, record.v) = Sphere::uv(&outward_n);
+
+    Some(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::Lambertian, vec3::Colour};
+
+    #[test]
+    fn uv() {
+        let check = |p: Point, u: f64, v: f64| {
+            let (su, sv) = Sphere::uv(&p);
+            assert!((su - u).abs() < 1e-12, "u({p}) = {su}, expected {u}");
+            assert!((sv - v).abs() < 1e-12, "v({p}) = {sv}, expected {v}");
+        };
+
+        check(Point::new(1., 0., 0.), 0.5, 0.5);
+        check(Point::new(0., 0., 1.), 0.25, 0.5);
+        check(Point::new(0., 0., -1.), 0.75, 0.5);
+        check(Point::new(0., 1., 0.), 0.5, 1.);
+        check(Point::new(0., -1., 0.), 0.5, 0.);
+    }
+
+    #[test]
+    fn hit_sets_uv() {
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(&Colour::default()));
+        let sphere = Sphere::new(Point::new(0., 0., 0.), 2., mat);
+        let ray = Ray::new(&Point::new(0., 0., 5.), &Vec3::new(0., 0., -1.));
+        let rec = sphere.hit(&ray, 0.001, f64::INFINITY).unwrap();
+
+        assert!((rec.u - 0.25).abs() < 1e-12);
+        assert!((rec.v - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn moving_sphere() {
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(&Colour::default()));
+        let sphere = MovingSphere::new(
+            Point::new(0., 0., 0.),
+            0.,
+            Point::new(4., 0., 0.),
+            1.,
+            1.,
+            mat,
+        );
+        let ray = |x, t| Ray::new(&Point::new(x, 0., 5.), &Vec3::new(0., 0., -1.)).with_time(t);
+
+        assert!(sphere.hit(&ray(0., 0.), 0.001, f64::INFINITY).is_some());
+        assert!(sphere.hit(&ray(0., 1.), 0.001, f64::INFINITY).is_none());
+        assert!(sphere.hit(&ray(2., 0.5), 0.001, f64::INFINITY).is_some());
+        assert!(sphere.hit(&ray(4., 1.), 0.001, f64::INFINITY).is_some());
+
+        let bbox = sphere.bounding_box().unwrap();
+        assert_eq!((bbox.min.x, bbox.max.x), (-1., 5.));
+    }
+
+    #[test]
+    fn keyframed_sphere() {
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(&Colour::default()));
+        let path = Keyframes::new(vec![
+            (0., Point::new(0., 0., 0.)),
+            (1., Point::new(0., 4., 0.)),
+            (2., Point::new(4., 4., 0.)),
+        ]);
+        let sphere = MovingSphere::keyframed(path, 1., mat);
+        let ray = |x, y, t| Ray::new(&Point::new(x, y, 5.), &Vec3::new(0., 0., -1.)).with_time(t);
 
-        let mut record = HitRecord::new(&self.mat);
-        record.t = root;
-        record.p = ray.at(root);
-        let outward_n = (record.p - self.centre) / self.r;
-        record.set_face_normal(ray, outward_n);
+        assert!(sphere
+            .hit(&ray(0., 2., 0.5), 0.001, f64::INFINITY)
+            .is_some());
+        assert!(sphere
+            .hit(&ray(2., 4., 1.5), 0.001, f64::INFINITY)
+            .is_some());
+        assert!(sphere.hit(&ray(2., 2., 1.), 0.001, f64::INFINITY).is_none());
 
-        Some(record)
+        let bbox = sphere.bounding_box().unwrap();
+        assert_eq!(
+            (bbox.min.x, bbox.min.y, bbox.max.x, bbox.max.y),
+            (-1., -1., 5., 5.)
+        );
     }
 }
@@ -0,0 +1,540 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use image::{ColorType, ImageError, ImageReader};
+use serde::Deserialize;
+
+use crate::{
+    colour::srgb_decode,
+    noise::{Perlin, Worley},
+    vec3::{Colour, Point},
+};
+
+/// Colour source evaluated at a hit's surface coordinates and position.
+pub trait Texture: Sync + Send {
+    fn value(&self, u: f64, v: f64, p: &Point) -> Colour;
+}
+
+pub struct SolidColour {
+    pub colour: Colour,
+}
+
+impl SolidColour {
+    pub fn new(colour: &Colour) -> Self {
+        Self { colour: *colour }
+    }
+}
+
+impl Texture for SolidColour {
+    fn value(&self, _u: f64, _v: f64, _p: &Point) -> Colour {
+        self.colour
+    }
+}
+
+/// 3D checkerboard of cubes with side `scale`, alternating between two textures.
+pub struct Checker {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl Checker {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        Self {
+            inv_scale: 1. / scale,
+            even,
+            odd,
+        }
+    }
+
+    pub fn from_colours(scale: f64, even: &Colour, odd: &Colour) -> Self {
+        Self::new(
+            scale,
+            Arc::new(SolidColour::new(even)),
+            Arc::new(SolidColour::new(odd)),
+        )
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, u: f64, v: f64, p: &Point) -> Colour {
+        let cell = |c: f64| (c * self.inv_scale).floor() as i64;
+        let sum = cell(p.x) + cell(p.y) + cell(p.z);
+
+        if sum.rem_euclid(2) == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoisePattern {
+    /// Plain Perlin noise.
+    #[default]
+    Perlin,
+    /// Fractal turbulence.
+    Fbm,
+    /// Sine bands along z, distorted by turbulence.
+    Marble,
+    /// Concentric rings around the y axis, distorted by turbulence.
+    Wood,
+    /// Distance to the nearest cell feature point.
+    Worley,
+}
+
+/// Procedural solid texture blending from `low` to `high` by a noise pattern
+/// evaluated at the hit point.
+pub struct NoiseTexture {
+    pattern: NoisePattern,
+    scale: f64,
+    octaves: u32,
+    turbulence: f64,
+    low: Colour,
+    high: Colour,
+    perlin: Perlin,
+    worley: Worley,
+}
+
+impl NoiseTexture {
+    /// Black to white noise at frequency `scale`. Equal seeds give equal textures.
+    pub fn new(pattern: NoisePattern, scale: f64, seed: u64) -> Self {
+        Self {
+            pattern,
+            scale,
+            octaves: 7,
+            turbulence: 10.,
+            low: Colour::new(0., 0., 0.),
+            high: Colour::new(1., 1., 1.),
+            perlin: Perlin::new(seed),
+            worley: Worley::new(seed),
+        }
+    }
+
+    pub fn with_colours(mut self, low: &Colour, high: &Colour) -> Self {
+        self.low = *low;
+        self.high = *high;
+        self
+    }
+
+    pub fn with_octaves(mut self, octaves: u32) -> Self {
+        self.octaves = octaves;
+        self
+    }
+
+    /// Strength of the turbulent distortion of marble and wood.
+    pub fn with_turbulence(mut self, turbulence: f64) -> Self {
+        self.turbulence = turbulence;
+        self
+    }
+
+    /// Blend factor in `[0, 1]` at `p`.
+    fn blend(&self, p: &Point) -> f64 {
+        let p = self.scale * *p;
+        let t = match self.pattern {
+            NoisePattern::Perlin => 0.5 * (1. + self.perlin.noise(&p)),
+            NoisePattern::Fbm => self.perlin.turbulence(&p, self.octaves),
+            NoisePattern::Marble => {
+                let turb = self.perlin.turbulence(&p, self.octaves);
+                0.5 * (1. + (p.z + self.turbulence * turb).sin())
+            }
+            NoisePattern::Wood => {
+                let turb = self.perlin.turbulence(&p, self.octaves);
+                let rings = p.x.hypot(p.z) + 0.1 * self.turbulence * turb;
+                rings - rings.floor()
+            }
+            NoisePattern::Worley => self.worley.distance(&p),
+        };
+
+        t.clamp(0., 1.)
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point) -> Colour {
+        let t = self.blend(p);
+        (1. - t) * self.low + t * self.high
+    }
+}
+
+#[derive(Debug)]
+pub enum TextureError {
+    Io { path: PathBuf, source: io::Error },
+    Unsupported { path: PathBuf, msg: String },
+    Decode { path: PathBuf, msg: String },
+}
+
+impl Display for TextureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextureError::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            TextureError::Unsupported { path, msg } => {
+                write!(f, "{}: unsupported image: {msg}", path.display())
+            }
+            TextureError::Decode { path, msg } => {
+                write!(f, "{}: cannot decode image: {msg}", path.display())
+            }
+        }
+    }
+}
+
+impl Error for TextureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TextureError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// How texture coordinates outside `[0, 1]` are mapped back onto the image.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WrapMode {
+    #[default]
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+impl WrapMode {
+    fn apply(&self, i: i64, n: usize) -> usize {
+        let n = n as i64;
+        let i = match self {
+            WrapMode::Repeat => i.rem_euclid(n),
+            WrapMode::Clamp => i.clamp(0, n - 1),
+            WrapMode::Mirror => {
+                let m = i.rem_euclid(2 * n);
+                if m < n {
+                    m
+                } else {
+                    2 * n - 1 - m
+                }
+            }
+        };
+        i as usize
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Filter {
+    Nearest,
+    #[default]
+    Bilinear,
+}
+
+/// Texture backed by a bitmap of linear colours, stored row by row from the
+/// top-left pixel and sampled with `v` pointing up.
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Colour>,
+    wrap: WrapMode,
+    filter: Filter,
+}
+
+impl ImageTexture {
+    pub fn new(width: usize, height: usize, pixels: Vec<Colour>) -> Self {
+        assert_eq!(pixels.len(), width * height, "pixel count mismatch");
+        assert!(width > 0 && height > 0, "empty image");
+        Self {
+            width,
+            height,
+            pixels,
+            wrap: WrapMode::default(),
+            filter: Filter::default(),
+        }
+    }
+
+    /// Loads a PNG, JPEG, Radiance HDR or OpenEXR image.
+    ///
+    /// 8 and 16-bit images are assumed to be sRGB encoded and are converted to
+    /// linear values; floating-point images are used as they are.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TextureError> {
+        let path = path.as_ref();
+        let reader = ImageReader::open(path)
+            .and_then(|r| r.with_guessed_format())
+            .map_err(|source| TextureError::Io {
+                path: path.to_path_buf(),
+                source,
+            })?;
+
+        let img = reader.decode().map_err(|e| match e {
+            ImageError::IoError(source) => TextureError::Io {
+                path: path.to_path_buf(),
+                source,
+            },
+            ImageError::Unsupported(e) => TextureError::Unsupported {
+                path: path.to_path_buf(),
+                msg: e.to_string(),
+            },
+            e => TextureError::Decode {
+                path: path.to_path_buf(),
+                msg: e.to_string(),
+            },
+        })?;
+
+        let is_float = matches!(img.color(), ColorType::Rgb32F | ColorType::Rgba32F);
+        let decode = |c: f32| {
+            if is_float {
+                c as f64
+            } else {
+                srgb_decode(c as f64)
+            }
+        };
+
+        let rgb = img.to_rgb32f();
+        let pixels = rgb
+            .pixels()
+            .map(|p| Colour::new(decode(p[0]), decode(p[1]), decode(p[2])))
+            .collect();
+
+        Ok(Self::new(
+            rgb.width() as usize,
+            rgb.height() as usize,
+            pixels,
+        ))
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Colour {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    fn texel(&self, x: i64, y: i64) -> Colour {
+        self.pixel(
+            self.wrap.apply(x, self.width),
+            self.wrap.apply(y, self.height),
+        )
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point) -> Colour {
+        // Continuous pixel coordinates, with y growing downwards.
+        let s = u * self.width as f64;
+        let t = (1. - v) * self.height as f64;
+
+        match self.filter {
+            Filter::Nearest => self.texel(s.floor() as i64, t.floor() as i64),
+            Filter::Bilinear => {
+                // Texel centres sit at half-integer coordinates.
+                let (s, t) = (s - 0.5, t - 0.5);
+                let (x, y) = (s.floor(), t.floor());
+                let (fx, fy) = (s - x, t - y);
+                let (x, y) = (x as i64, y as i64);
+
+                let top = (1. - fx) * self.texel(x, y) + fx * self.texel(x + 1, y);
+                let bottom = (1. - fx) * self.texel(x, y + 1) + fx * self.texel(x + 1, y + 1);
+                (1. - fy) * top + fy * bottom
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solid() {
+        let t = SolidColour::new(&Colour::new(0.1, 0.2, 0.3));
+        let c = t.value(0.7, 0.2, &Point::new(5., -3., 1.));
+        assert_eq!((c.x, c.y, c.z), (0.1, 0.2, 0.3));
+    }
+
+    #[test]
+    fn noise_textures() {
+        let low = Colour::new(0.1, 0.2, 0.3);
+        let high = Colour::new(0.9, 0.8, 0.7);
+        let patterns = [
+            NoisePattern::Perlin,
+            NoisePattern::Fbm,
+            NoisePattern::Marble,
+            NoisePattern::Wood,
+            NoisePattern::Worley,
+        ];
+
+        for pattern in patterns {
+            let t = NoiseTexture::new(pattern, 4., 42).with_colours(&low, &high);
+            let same = NoiseTexture::new(pattern, 4., 42).with_colours(&low, &high);
+            let other = NoiseTexture::new(pattern, 4., 43).with_colours(&low, &high);
+
+            let mut differs = false;
+            for i in 0..200 {
+                let i = i as f64;
+                let p = Point::new(i * 0.173, (i * 0.37).sin(), i * -0.051);
+                let c = t.value(0., 0., &p);
+
+                assert!(c.x >= low.x - 1e-12 && c.x <= high.x + 1e-12, "{pattern:?}");
+                assert!(c.z >= low.z - 1e-12 && c.z <= high.z + 1e-12, "{pattern:?}");
+                let rgb = |c: Colour| (c.x, c.y, c.z);
+                assert_eq!(rgb(c), rgb(same.value(0., 0., &p)), "{pattern:?}");
+                differs |= rgb(c) != rgb(other.value(0., 0., &p));
+            }
+            assert!(differs, "{pattern:?} ignores its seed");
+        }
+    }
+
+    #[test]
+    fn checker_alternates() {
+        let t = Checker::from_colours(0.5, &Colour::new(1., 1., 1.), &Colour::new(0., 0., 0.));
+
+        let at = |x, y, z| t.value(0., 0., &Point::new(x, y, z)).x;
+        assert_eq!(at(0.1, 0.1, 0.1), 1.);
+        assert_eq!(at(0.6, 0.1, 0.1), 0.);
+        assert_eq!(at(0.6, 0.6, 0.1), 1.);
+        assert_eq!(at(-0.1, 0.1, 0.1), 0.);
+        assert_eq!(at(-0.1, -0.1, -0.1), 0.);
+    }
+
+    #[test]
+    fn image_lookup() {
+        // 2x2: top row red/green, bottom row blue/white.
+        let t = ImageTexture::new(
+            2,
+            2,
+            vec![
+                Colour::new(1., 0., 0.),
+                Colour::new(0., 1., 0.),
+                Colour::new(0., 0., 1.),
+                Colour::new(1., 1., 1.),
+            ],
+        );
+        let t = t.with_filter(Filter::Nearest).with_wrap(WrapMode::Clamp);
+        let p = Point::default();
+
+        assert_eq!(t.value(0.25, 0.75, &p).x, 1.);
+        assert_eq!(t.value(0.75, 0.75, &p).y, 1.);
+        assert_eq!(t.value(0.25, 0.25, &p).z, 1.);
+        assert_eq!(t.value(1., 0., &p).x, 1.);
+        assert_eq!(t.value(1., 0., &p).y, 1.);
+    }
+
+    fn gradient() -> ImageTexture {
+        // One row: 0, 1, 2, 3 in the red channel.
+        let pixels = (0..4).map(|i| Colour::new(i as f64, 0., 0.)).collect();
+        ImageTexture::new(4, 1, pixels)
+    }
+
+    #[test]
+    fn wrap_modes() {
+        assert_eq!(WrapMode::Repeat.apply(-1, 4), 3);
+        assert_eq!(WrapMode::Repeat.apply(5, 4), 1);
+        assert_eq!(WrapMode::Clamp.apply(-1, 4), 0);
+        assert_eq!(WrapMode::Clamp.apply(5, 4), 3);
+        assert_eq!(WrapMode::Mirror.apply(-1, 4), 0);
+        assert_eq!(WrapMode::Mirror.apply(4, 4), 3);
+        assert_eq!(WrapMode::Mirror.apply(5, 4), 2);
+        assert_eq!(WrapMode::Mirror.apply(8, 4), 0);
+
+        let p = Point::default();
+        let t = gradient().with_filter(Filter::Nearest);
+        assert_eq!(t.value(1.125, 0.5, &p).x, 0.);
+
+        let t = t.with_wrap(WrapMode::Clamp);
+        assert_eq!(t.value(1.125, 0.5, &p).x, 3.);
+
+        let t = t.with_wrap(WrapMode::Mirror);
+        assert_eq!(t.value(1.125, 0.5, &p).x, 3.);
+        assert_eq!(t.value(1.375, 0.5, &p).x, 2.);
+    }
+
+    #[test]
+    fn bilinear() {
+        let p = Point::default();
+        let t = gradient().with_wrap(WrapMode::Clamp);
+
+        // Texel centres are exact, halfway between is the average.
+        assert!((t.value(0.125, 0.5, &p).x - 0.).abs() < 1e-12);
+        assert!((t.value(0.375, 0.5, &p).x - 1.).abs() < 1e-12);
+        assert!((t.value(0.25, 0.5, &p).x - 0.5).abs() < 1e-12);
+        assert!((t.value(0.3125, 0.5, &p).x - 0.75).abs() < 1e-12);
+
+        // Clamped edges do not blend with the opposite side...
+        assert!((t.value(0., 0.5, &p).x - 0.).abs() < 1e-12);
+
+        // ...repeating ones do.
+        let t = gradient();
+        assert!((t.value(0., 0.5, &p).x - 1.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn load_png() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("checks.png");
+        let img = image::RgbImage::from_fn(2, 1, |x, _| {
+            if x == 0 {
+                image::Rgb([255, 128, 0])
+            } else {
+                image::Rgb([0, 0, 0])
+            }
+        });
+        img.save(&path).unwrap();
+
+        let t = ImageTexture::load(&path).unwrap();
+        assert_eq!((t.width(), t.height()), (2, 1));
+
+        let c = t.pixel(0, 0);
+        assert!((c.x - 1.).abs() < 1e-6);
+        assert!((c.y - srgb_decode(128. / 255.)).abs() < 1e-6);
+        assert_eq!(c.z, 0.);
+    }
+
+    #[test]
+    fn load_hdr_is_linear() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sky.exr");
+        let img = image::Rgb32FImage::from_pixel(3, 2, image::Rgb([4., 0.5, 0.25]));
+        img.save(&path).unwrap();
+
+        let t = ImageTexture::load(&path).unwrap();
+        assert_eq!((t.width(), t.height()), (3, 2));
+
+        let c = t.pixel(2, 1);
+        assert_eq!((c.x, c.y, c.z), (4., 0.5, 0.25));
+    }
+
+    #[test]
+    fn load_errors() {
+        let err = ImageTexture::load("no/such/image.png").err().unwrap();
+        assert!(matches!(err, TextureError::Io { .. }));
+        assert!(err.to_string().starts_with("no/such/image.png: "));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "not an image").unwrap();
+        let err = ImageTexture::load(&path).err().unwrap();
+        assert!(matches!(err, TextureError::Unsupported { .. }), "{err}");
+
+        let path = dir.path().join("broken.png");
+        std::fs::write(&path, b"\x89PNG\r\n\x1a\ntruncated").unwrap();
+        let err = ImageTexture::load(&path).err().unwrap();
+        assert!(matches!(err, TextureError::Decode { .. }), "{err}");
+    }
+}
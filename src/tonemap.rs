@@ -0,0 +1,261 @@
+//! Tone mapping: compressing the unbounded radiance of a render into the
+//! displayable range, instead of clipping highlights.
+
+use serde::Deserialize;
+
+use crate::{
+    colour::{apply, srgb_decode, Matrix},
+    framebuffer::Framebuffer,
+    vec3::{Colour, Vec3},
+};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToneMapper {
+    /// Values above 1 are clipped.
+    #[default]
+    Clip,
+    /// `x / (1 + x)` per channel, which never quite reaches white.
+    Reinhard,
+    /// Reinhard with a white point that maps exactly to 1.
+    ExtendedReinhard,
+    /// Stephen Hill's fit of the ACES reference rendering and sRGB output
+    /// transforms.
+    AcesFilmic,
+    /// John Hable's filmic curve from Uncharted 2.
+    Hable,
+    /// Troy Sobotka's AgX, which desaturates highlights smoothly towards white.
+    Agx,
+}
+
+/// A tone mapper with its settings. Operates on linear sRGB.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToneMapping {
+    #[serde(default)]
+    pub operator: ToneMapper,
+    /// Exposure adjustment in stops, applied before the curve.
+    #[serde(default)]
+    pub exposure: f64,
+    /// Linear value mapped to white by the extended Reinhard and Hable
+    /// curves. Defaults to 4 and 11.2 respectively.
+    pub white: Option<f64>,
+}
+
+impl ToneMapping {
+    pub fn new(operator: ToneMapper) -> Self {
+        Self {
+            operator,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_exposure(mut self, stops: f64) -> Self {
+        self.exposure = stops;
+        self
+    }
+
+    pub fn with_white(mut self, white: f64) -> Self {
+        self.white = Some(white);
+        self
+    }
+
+    /// Maps a linear colour to display-referred linear sRGB, within `[0, 1]`
+    /// for every operator but `Clip`.
+    pub fn map(&self, c: &Colour) -> Colour {
+        let c = self.exposure.exp2() * *c;
+        let per_channel = |f: &dyn Fn(f64) -> f64| Colour::new(f(c.x), f(c.y), f(c.z));
+
+        match self.operator {
+            ToneMapper::Clip => c,
+            ToneMapper::Reinhard => per_channel(&|x| x / (1. + x)),
+            ToneMapper::ExtendedReinhard => {
+                let w2 = self.white.unwrap_or(4.).powi(2);
+                per_channel(&|x| (x * (1. + x / w2) / (1. + x)).min(1.))
+            }
+            ToneMapper::AcesFilmic => aces_filmic(&c),
+            ToneMapper::Hable => {
+                let white = hable(self.white.unwrap_or(11.2));
+                per_channel(&|x| (hable(x) / white).min(1.))
+            }
+            ToneMapper::Agx => agx(&c),
+        }
+    }
+
+    pub fn apply(&self, image: &mut Framebuffer) {
+        if self.operator == ToneMapper::Clip && self.exposure == 0. {
+            return;
+        }
+        for px in image.pixels_mut() {
+            *px = self.map(px);
+        }
+    }
+}
+
+/// sRGB to the ACES rendering space, with the saturation tweak of the
+/// reference rendering transform folded in.
+const ACES_INPUT: Matrix = [
+    Vec3::new(0.59719, 0.35458, 0.04823),
+    Vec3::new(0.07600, 0.90834, 0.01566),
+    Vec3::new(0.02840, 0.13383, 0.83777),
+];
+
+const ACES_OUTPUT: Matrix = [
+    Vec3::new(1.60475, -0.53108, -0.07367),
+    Vec3::new(-0.10208, 1.10813, -0.00605),
+    Vec3::new(-0.00327, -0.07276, 1.07602),
+];
+
+fn aces_filmic(c: &Colour) -> Colour {
+    let v = apply(&ACES_INPUT, c);
+    let fit = |v: f64| {
+        let a = v * (v + 0.024_578_6) - 0.000_090_537;
+        let b = v * (0.983_729 * v + 0.432_951) + 0.238_081;
+        a / b
+    };
+    let v = apply(&ACES_OUTPUT, &Colour::new(fit(v.x), fit(v.y), fit(v.z)));
+    Colour::new(v.x.clamp(0., 1.), v.y.clamp(0., 1.), v.z.clamp(0., 1.))
+}
+
+fn hable(x: f64) -> f64 {
+    const A: f64 = 0.15;
+    const B: f64 = 0.50;
+    const C: f64 = 0.10;
+    const D: f64 = 0.20;
+    const E: f64 = 0.02;
+    const F: f64 = 0.30;
+    (x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F) - E / F
+}
+
+/// Squeezes the sRGB primaries inwards, so that bright saturated colours
+/// head towards white.
+const AGX_INSET: Matrix = [
+    Vec3::new(
+        0.842_479_062_253_094,
+        0.078_433_599_999_999_2,
+        0.079_223_745_147_764_3,
+    ),
+    Vec3::new(
+        0.042_328_242_261_012_3,
+        0.878_468_636_469_772,
+        0.079_166_127_460_543_4,
+    ),
+    Vec3::new(0.042_375_654_905_705_1, 0.078_433_6, 0.879_142_973_793_104),
+];
+
+const AGX_OUTSET: Matrix = [
+    Vec3::new(
+        1.196_879_005_120_17,
+        -0.098_020_881_140_136_8,
+        -0.099_029_744_079_720_5,
+    ),
+    Vec3::new(
+        -0.052_896_851_757_456_2,
+        1.151_903_129_904_17,
+        -0.098_961_176_844_843_3,
+    ),
+    Vec3::new(
+        -0.052_971_635_514_443_8,
+        -0.098_043_450_117_124_1,
+        1.151_073_672_641_16,
+    ),
+];
+
+/// AgX with its default look, following the polynomial fit of the sigmoid
+/// by Benjamin Wrensch.
+fn agx(c: &Colour) -> Colour {
+    const MIN_EV: f64 = -12.473_931_188;
+    const MAX_EV: f64 = 4.026_068_812;
+
+    let v = apply(&AGX_INSET, c);
+    let curve = |x: f64| {
+        // Log encoding over the range of exposures the curve handles.
+        let x = (x.max(1e-10).log2().clamp(MIN_EV, MAX_EV) - MIN_EV) / (MAX_EV - MIN_EV);
+        let (x2, x4) = (x * x, x * x * x * x);
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+            - 0.00232
+    };
+    let v = apply(
+        &AGX_OUTSET,
+        &Colour::new(curve(v.x), curve(v.y), curve(v.z)),
+    );
+
+    // The curve produces sRGB-encoded values; return linear light.
+    let linear = |x: f64| srgb_decode(x.clamp(0., 1.));
+    Colour::new(linear(v.x), linear(v.y), linear(v.z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVES: [ToneMapper; 5] = [
+        ToneMapper::Reinhard,
+        ToneMapper::ExtendedReinhard,
+        ToneMapper::AcesFilmic,
+        ToneMapper::Hable,
+        ToneMapper::Agx,
+    ];
+
+    fn grey(tm: &ToneMapping, x: f64) -> f64 {
+        tm.map(&Colour::new(x, x, x)).y
+    }
+
+    #[test]
+    fn monotonic_and_bounded() {
+        for op in CURVES {
+            let tm = ToneMapping::new(op);
+            let mut last = grey(&tm, 0.);
+            assert!(last.abs() < 1e-3, "{op:?} maps black to {last}");
+
+            for i in 1..=2000 {
+                let x = (i as f64 / 100.).powi(2);
+                let y = grey(&tm, x);
+                assert!(y >= last, "{op:?} decreases at {x}");
+                assert!(y <= 1., "{op:?} exceeds white at {x}");
+                last = y;
+            }
+            assert!(last > 0.95, "{op:?} only reaches {last}");
+
+            // Channels are mapped independently of each other's order.
+            let c = tm.map(&Colour::new(0.1, 1., 10.));
+            assert!(c.x < c.y && c.y < c.z, "{op:?}: {c}");
+        }
+    }
+
+    #[test]
+    fn known_values() {
+        let reinhard = ToneMapping::new(ToneMapper::Reinhard);
+        assert_eq!(grey(&reinhard, 1.), 0.5);
+        assert_eq!(grey(&reinhard, 3.), 0.75);
+
+        let extended = ToneMapping::new(ToneMapper::ExtendedReinhard).with_white(2.);
+        assert!((grey(&extended, 2.) - 1.).abs() < 1e-12);
+        assert!((grey(&extended, 1.) - 0.625).abs() < 1e-12);
+
+        let hable = ToneMapping::new(ToneMapper::Hable);
+        assert!((grey(&hable, 11.2) - 1.).abs() < 1e-12);
+        assert!((super::hable(1.) - 0.220_657).abs() < 1e-6);
+
+        // ACES darkens mid grey to leave room for highlights, AgX puts it
+        // half way up the display-encoded range.
+        let aces = ToneMapping::new(ToneMapper::AcesFilmic);
+        assert!((grey(&aces, 0.18) - 0.1056).abs() < 1e-4);
+        let agx = ToneMapping::new(ToneMapper::Agx);
+        assert!((grey(&agx, 0.18) - 0.2110).abs() < 1e-4);
+
+        let clip = ToneMapping::default();
+        assert_eq!(grey(&clip, 5.), 5.);
+    }
+
+    #[test]
+    fn exposure_in_stops() {
+        let tm = ToneMapping::new(ToneMapper::Reinhard).with_exposure(1.);
+        assert_eq!(grey(&tm, 0.5), 0.5);
+
+        let mut image = Framebuffer::from_pixels(1, 1, vec![Colour::new(1., 3., 0.)]);
+        ToneMapping::new(ToneMapper::Reinhard).apply(&mut image);
+        let c = image.get(0, 0);
+        assert_eq!((c.x, c.y, c.z), (0.5, 0.75, 0.));
+    }
+}
@@ -0,0 +1,250 @@
+//! Placing and moving objects with rigid transforms.
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    motion::{Keyframes, Lerp},
+    ray::Ray,
+    vec3::{Point, Vec3},
+};
+
+/// Uniform scale, then rotation about the x, y and z axes in that order
+/// (degrees), then translation.
+#[derive(Copy, Clone, Debug)]
+pub struct Transform {
+    pub translate: Vec3,
+    pub rotate: Vec3,
+    pub scale: f64,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            translate: Vec3::default(),
+            rotate: Vec3::default(),
+            scale: 1.,
+        }
+    }
+}
+
+impl Lerp for Transform {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        Self {
+            translate: self.translate.lerp(&other.translate, t),
+            rotate: self.rotate.lerp(&other.rotate, t),
+            scale: self.scale.lerp(&other.scale, t),
+        }
+    }
+}
+
+impl Transform {
+    /// Rows of the rotation matrix.
+    fn rotation(&self) -> [Vec3; 3] {
+        let (sx, cx) = self.rotate.x.to_radians().sin_cos();
+        let (sy, cy) = self.rotate.y.to_radians().sin_cos();
+        let (sz, cz) = self.rotate.z.to_radians().sin_cos();
+
+        // Rz * Ry * Rx
+        [
+            Vec3::new(cz * cy, cz * sy * sx - sz * cx, cz * sy * cx + sz * sx),
+            Vec3::new(sz * cy, sz * sy * sx + cz * cx, sz * sy * cx - cz * sx),
+            Vec3::new(-sy, cy * sx, cy * cx),
+        ]
+    }
+
+    fn rotate(&self, v: &Vec3) -> Vec3 {
+        let [r0, r1, r2] = self.rotation();
+        Vec3::new(r0.dot(v), r1.dot(v), r2.dot(v))
+    }
+
+    fn unrotate(&self, v: &Vec3) -> Vec3 {
+        let [r0, r1, r2] = self.rotation();
+        v.x * r0 + v.y * r1 + v.z * r2
+    }
+
+    pub fn point(&self, p: &Point) -> Point {
+        self.scale * self.rotate(p) + self.translate
+    }
+
+    pub fn vector(&self, v: &Vec3) -> Vec3 {
+        self.scale * self.rotate(v)
+    }
+
+    /// Normals only rotate, the scale being uniform.
+    pub fn normal(&self, n: &Vec3) -> Vec3 {
+        self.rotate(n)
+    }
+
+    pub fn inverse_point(&self, p: &Point) -> Point {
+        self.unrotate(&(*p - self.translate)) / self.scale
+    }
+
+    pub fn inverse_vector(&self, v: &Vec3) -> Vec3 {
+        self.unrotate(v) / self.scale
+    }
+}
+
+/// Any object placed by a transform, which may change over time.
+pub struct Transformed {
+    object: Box<dyn Hittable>,
+    motion: Keyframes<Transform>,
+}
+
+impl Transformed {
+    pub fn new(object: impl Hittable + 'static, transform: Transform) -> Self {
+        Self::animated(object, Keyframes::constant(transform))
+    }
+
+    pub fn animated(object: impl Hittable + 'static, motion: Keyframes<Transform>) -> Self {
+        Self {
+            object: Box::new(object),
+            motion,
+        }
+    }
+}
+
+impl Hittable for Transformed {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let tf = self.motion.at(ray.time());
+
+        // The ray parameter is the same in both spaces.
+        let local = Ray::new(
+            &tf.inverse_point(&ray.origin()),
+            &tf.inverse_vector(&ray.dir()),
+        )
+        .with_time(ray.time());
+        let mut rec = self.object.hit(&local, t_min, t_max)?;
+
+        rec.p = tf.point(&rec.p);
+        rec.n = tf.normal(&rec.n);
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let bbox = self.object.bounding_box()?;
+        let corners: Vec<Point> = (0..8)
+            .map(|i| {
+                let pick = |bit, axis| {
+                    if i & bit == 0 {
+                        bbox.min[axis]
+                    } else {
+                        bbox.max[axis]
+                    }
+                };
+                Point::new(pick(1, 0), pick(2, 1), pick(4, 2))
+            })
+            .collect();
+
+        // Without rotation every corner is a linear function of the scale and
+        // translation, so the hull of the keyframed transforms bounds it.
+        // Rotating objects stay within a sphere around their moving origin.
+        let hull = self.motion.hull();
+        let rotates = hull
+            .iter()
+            .any(|tf| (tf.rotate - hull[0].rotate).len_squared() > 0.);
+        let radius = corners.iter().map(|c| c.len()).fold(0., f64::max);
+
+        let key_box = |tf: &Transform| {
+            if rotates {
+                let r = Vec3::new(1., 1., 1.) * tf.scale * radius;
+                Aabb::new(&(tf.translate - r), &(tf.translate + r))
+            } else {
+                let p = tf.point(&corners[0]);
+                corners[1..]
+                    .iter()
+                    .fold(Aabb::new(&p, &p), |b, c| b.union_point(&tf.point(c)))
+            }
+        };
+        let bounds = hull.iter().map(key_box).reduce(|a, b| a.union(&b)).unwrap();
+
+        Some(bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{material::Lambertian, sphere::Sphere, triangle::Triangle, vec3::Colour};
+
+    fn transform(translate: [f64; 3], rotate: [f64; 3], scale: f64) -> Transform {
+        Transform {
+            translate: Vec3::new(translate[0], translate[1], translate[2]),
+            rotate: Vec3::new(rotate[0], rotate[1], rotate[2]),
+            scale,
+        }
+    }
+
+    #[test]
+    fn inverse_round_trips() {
+        let tf = transform([1., -2., 3.], [30., 45., -60.], 2.5);
+        let p = Point::new(0.3, -0.7, 1.1);
+
+        assert!((tf.inverse_point(&tf.point(&p)) - p).len() < 1e-12);
+        assert!((tf.inverse_vector(&tf.vector(&p)) - p).len() < 1e-12);
+        assert!((tf.normal(&p).len() - p.len()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rotation_order() {
+        // x first: +y turns to +z, then y turns +z to +x.
+        let tf = transform([0., 0., 0.], [90., 90., 0.], 1.);
+        let v = tf.vector(&Vec3::new(0., 1., 0.));
+        assert!((v - Vec3::new(1., 0., 0.)).len() < 1e-12, "{v}");
+    }
+
+    #[test]
+    fn transformed_hit() {
+        let mat = Arc::new(Lambertian::new(&Colour::new(0.5, 0.5, 0.5)));
+        let sphere = Sphere::new(Point::new(0., 0., 0.), 1., mat);
+        let moved = Transformed::new(sphere, transform([5., 0., 0.], [0., 0., 90.], 2.));
+
+        let ray = Ray::new(&Point::new(5., 0., 10.), &Vec3::new(0., 0., -1.));
+        let rec = moved.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 8.).abs() < 1e-9);
+        assert!((rec.p - Point::new(5., 0., 2.)).len() < 1e-9);
+        assert!((rec.n - Vec3::new(0., 0., 1.)).len() < 1e-9);
+
+        let bbox = moved.bounding_box().unwrap();
+        assert!((bbox.min - Point::new(3., -2., -2.)).len() < 1e-9);
+        assert!((bbox.max - Point::new(7., 2., 2.)).len() < 1e-9);
+    }
+
+    #[test]
+    fn moving_bounds_cover_motion() {
+        let mat = Arc::new(Lambertian::new(&Colour::new(0.5, 0.5, 0.5)));
+        let tri = Triangle::new(
+            Point::new(1., 0., 0.),
+            Point::new(2., 0., 0.),
+            Point::new(1., 1., 0.),
+            mat,
+        );
+        let motion = Keyframes::new(vec![
+            (0., transform([0., 0., 0.], [0., 0., 0.], 1.)),
+            (1., transform([3., 0., 0.], [0., 0., 0.], 2.)),
+            (2., transform([3., 0., 0.], [0., 180., 0.], 2.)),
+        ]);
+        let moving = Transformed::animated(tri, motion.clone());
+        let bbox = moving.bounding_box().unwrap();
+
+        for i in 0..=40 {
+            let tf = motion.at(i as f64 / 20.);
+            for c in [
+                Point::new(1., 0., 0.),
+                Point::new(2., 0., 0.),
+                Point::new(1., 1., 0.),
+            ] {
+                let p = tf.point(&c);
+                for axis in 0..3 {
+                    assert!(p[axis] >= bbox.min[axis] - 1e-9 && p[axis] <= bbox.max[axis] + 1e-9);
+                }
+            }
+        }
+
+        // A ray only meets the triangle where it is at the ray's time.
+        let ray = |t| Ray::new(&Point::new(1.2, 0.2, 5.), &Vec3::new(0., 0., -1.)).with_time(t);
+        assert!(moving.hit(&ray(0.), 0.001, f64::INFINITY).is_some());
+        assert!(moving.hit(&ray(1.), 0.001, f64::INFINITY).is_none());
+    }
+}
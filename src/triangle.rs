@@ -0,0 +1,267 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+    ray::Ray,
+    vec3::{Point, Vec3},
+};
+
+pub type Uv = (f64, f64);
+
+/// Watertight ray/triangle intersection (Woop, Benthin and Wald 2013).
+///
+/// Returns the ray parameter and the barycentric weights of `p0`, `p1` and
+/// `p2`. Rays crossing an edge shared by two triangles hit at least one of
+/// them, so meshes have no cracks.
+pub fn intersect(
+    p0: &Point,
+    p1: &Point,
+    p2: &Point,
+    ray: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, [f64; 3])> {
+    let dir = ray.dir();
+    let orig = ray.origin();
+
+    // Permute axes so that the ray travels mostly along z.
+    let kz = if dir.x.abs() > dir.y.abs() && dir.x.abs() > dir.z.abs() {
+        0
+    } else if dir.y.abs() > dir.z.abs() {
+        1
+    } else {
+        2
+    };
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    if dir[kz] < 0. {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+
+    let sx = dir[kx] / dir[kz];
+    let sy = dir[ky] / dir[kz];
+    let sz = 1. / dir[kz];
+
+    let a = *p0 - orig;
+    let b = *p1 - orig;
+    let c = *p2 - orig;
+
+    let ax = a[kx] - sx * a[kz];
+    let ay = a[ky] - sy * a[kz];
+    let bx = b[kx] - sx * b[kz];
+    let by = b[ky] - sy * b[kz];
+    let cx = c[kx] - sx * c[kz];
+    let cy = c[ky] - sy * c[kz];
+
+    let e0 = cx * by - cy * bx;
+    let e1 = ax * cy - ay * cx;
+    let e2 = bx * ay - by * ax;
+
+    if (e0 < 0. || e1 < 0. || e2 < 0.) && (e0 > 0. || e1 > 0. || e2 > 0.) {
+        return None;
+    }
+
+    let det = e0 + e1 + e2;
+    if det == 0. {
+        return None;
+    }
+
+    let t = (e0 * sz * a[kz] + e1 * sz * b[kz] + e2 * sz * c[kz]) / det;
+    if t < t_min || t > t_max {
+        return None;
+    }
+
+    Some((t, [e0 / det, e1 / det, e2 / det]))
+}
+
+/// Fills in a hit record for a triangle hit.
+///
+/// Geometric orientation decides `front_face`; the optional per-vertex
+/// normals are interpolated and flipped into the same hemisphere.
+pub(crate) fn shade(
+    ray: &Ray,
+    t: f64,
+    bary: [f64; 3],
+    p: [&Point; 3],
+    n: Option<[&Vec3; 3]>,
+    uv: Option<[Uv; 3]>,
+    mat: &Arc<dyn Material>,
+) -> HitRecord {
+    let mut record = HitRecord::new(mat);
+    record.t = t;
+    record.p = ray.at(t);
+
+    let geometric_n = (*p[1] - *p[0]).cross(&(*p[2] - *p[0])).unit_vector();
+    record.set_face_normal(ray, geometric_n);
+
+    if let Some(n) = n {
+        let shading_n = (bary[0] * *n[0] + bary[1] * *n[1] + bary[2] * *n[2]).unit_vector();
+        if !shading_n.x.is_nan() {
+            record.n = if shading_n.dot(&record.n) < 0. {
+                -shading_n
+            } else {
+                shading_n
+            };
+        }
+    }
+
+    (record.u, record.v) = match uv {
+        Some(uv) => (
+            bary[0] * uv[0].0 + bary[1] * uv[1].0 + bary[2] * uv[2].0,
+            bary[0] * uv[0].1 + bary[1] * uv[1].1 + bary[2] * uv[2].1,
+        ),
+        None => (bary[1], bary[2]),
+    };
+
+    record
+}
+
+pub(crate) fn bounds(p0: &Point, p1: &Point, p2: &Point) -> Aabb {
+    // Pad flat boxes so that axis-aligned triangles still have volume.
+    let pad = Vec3::new(1e-6, 1e-6, 1e-6);
+    let bbox = Aabb::new(p0, p1).union_point(p2);
+    Aabb::new(&(bbox.min - pad), &(bbox.max + pad))
+}
+
+pub struct Triangle {
+    v: [Point; 3],
+    n: Option<[Vec3; 3]>,
+    uv: Option<[Uv; 3]>,
+    mat: Arc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(v0: Point, v1: Point, v2: Point, mat: Arc<dyn Material>) -> Self {
+        Self {
+            v: [v0, v1, v2],
+            n: None,
+            uv: None,
+            mat,
+        }
+    }
+
+    pub fn with_normals(mut self, n0: Vec3, n1: Vec3, n2: Vec3) -> Self {
+        self.n = Some([n0, n1, n2]);
+        self
+    }
+
+    pub fn with_uvs(mut self, uv0: Uv, uv1: Uv, uv2: Uv) -> Self {
+        self.uv = Some([uv0, uv1, uv2]);
+        self
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let [p0, p1, p2] = &self.v;
+        let (t, bary) = intersect(p0, p1, p2, ray, t_min, t_max)?;
+
+        Some(shade(
+            ray,
+            t,
+            bary,
+            [p0, p1, p2],
+            self.n.as_ref().map(|[n0, n1, n2]| [n0, n1, n2]),
+            self.uv,
+            &self.mat,
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(bounds(&self.v[0], &self.v[1], &self.v[2]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{material::Lambertian, vec3::Colour};
+
+    fn mat() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(&Colour::default()))
+    }
+
+    fn unit_triangle() -> Triangle {
+        Triangle::new(
+            Point::new(0., 0., 0.),
+            Point::new(1., 0., 0.),
+            Point::new(0., 1., 0.),
+            mat(),
+        )
+    }
+
+    #[test]
+    fn hit_front_face() {
+        let tri = unit_triangle();
+        let ray = Ray::new(&Point::new(0.25, 0.25, 2.), &Vec3::new(0., 0., -1.));
+        let rec = tri.hit(&ray, 0.001, f64::INFINITY).unwrap();
+
+        assert!((rec.t - 2.).abs() < 1e-12);
+        assert!(rec.front_face);
+        assert!((rec.n.z - 1.).abs() < 1e-12);
+        assert!((rec.u - 0.25).abs() < 1e-12);
+        assert!((rec.v - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn hit_back_face() {
+        let tri = unit_triangle();
+        let ray = Ray::new(&Point::new(0.25, 0.25, -2.), &Vec3::new(0., 0., 2.));
+        let rec = tri.hit(&ray, 0.001, f64::INFINITY).unwrap();
+
+        assert!((rec.t - 1.).abs() < 1e-12);
+        assert!(!rec.front_face);
+        assert!((rec.n.z + 1.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn miss() {
+        let tri = unit_triangle();
+
+        let outside = Ray::new(&Point::new(0.75, 0.75, 2.), &Vec3::new(0., 0., -1.));
+        assert!(tri.hit(&outside, 0.001, f64::INFINITY).is_none());
+
+        let parallel = Ray::new(&Point::new(-1., 0.25, 0.), &Vec3::new(1., 0., 0.));
+        assert!(tri.hit(&parallel, 0.001, f64::INFINITY).is_none());
+
+        let too_far = Ray::new(&Point::new(0.25, 0.25, 2.), &Vec3::new(0., 0., -1.));
+        assert!(tri.hit(&too_far, 0.001, 1.).is_none());
+    }
+
+    #[test]
+    fn shared_edge_is_watertight() {
+        let a = unit_triangle();
+        let b = Triangle::new(
+            Point::new(1., 0., 0.),
+            Point::new(1., 1., 0.),
+            Point::new(0., 1., 0.),
+            mat(),
+        );
+
+        for i in 1..100 {
+            let s = i as f64 / 100.;
+            let ray = Ray::new(&Point::new(s, 1. - s, 3.), &Vec3::new(0., 0., -1.));
+            let hits = a.hit(&ray, 0.001, f64::INFINITY).is_some() as u32
+                + b.hit(&ray, 0.001, f64::INFINITY).is_some() as u32;
+            assert!(hits >= 1, "ray through shared edge at {s} leaked");
+        }
+    }
+
+    #[test]
+    fn interpolated_normals_and_uvs() {
+        let n = Vec3::new(1., 0., 1.).unit_vector();
+        let tri = unit_triangle()
+            .with_normals(Vec3::new(0., 0., 1.), n, n)
+            .with_uvs((0., 0.), (2., 0.), (0., 4.));
+
+        let ray = Ray::new(&Point::new(0.5, 0.25, 1.), &Vec3::new(0., 0., -1.));
+        let rec = tri.hit(&ray, 0.001, f64::INFINITY).unwrap();
+
+        let expected = (0.25 * Vec3::new(0., 0., 1.) + 0.75 * n).unit_vector();
+        assert!((rec.n - expected).len() < 1e-12);
+        assert!((rec.u - 1.).abs() < 1e-12);
+        assert!((rec.v - 1.).abs() < 1e-12);
+    }
+}
@@ -1,12 +1,14 @@
 use std::{
     fmt::Display,
     iter::Sum,
-    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub},
+    ops::{Add, AddAssign, Div, DivAssign, Index, Mul, MulAssign, Neg, Sub},
 };
 
-use rand::Rng;
-
-use crate::constants::{MAX_CLAMP, MIN_CLAMP, MIN_DIM, SAMPLES_PER_PIXEL};
+use crate::{
+    colour::to_srgb8,
+    constants::{MAX_CLAMP, MIN_CLAMP, MIN_DIM},
+    rng::Rng,
+};
 
 pub fn clamp(x: f64) -> f64 {
     if x < MIN_CLAMP {
@@ -35,27 +37,25 @@ impl Sum for Vec3 {
 }
 
 impl Vec3 {
-    pub fn random() -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn random(rng: &mut Rng) -> Self {
         Self {
-            x: rng.gen_range(0.0..1.),
-            y: rng.gen_range(0.0..1.),
-            z: rng.gen_range(0.0..1.),
+            x: rng.random(),
+            y: rng.random(),
+            z: rng.random(),
         }
     }
 
-    pub fn random_bounded(min: f64, max: f64) -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn random_bounded(rng: &mut Rng, min: f64, max: f64) -> Self {
         Self {
-            x: rng.gen_range(min..max),
-            y: rng.gen_range(min..max),
-            z: rng.gen_range(min..max),
+            x: rng.random_range(min, max),
+            y: rng.random_range(min, max),
+            z: rng.random_range(min, max),
         }
     }
 
-    pub fn random_in_unit_sphere() -> Self {
+    pub fn random_in_unit_sphere(rng: &mut Rng) -> Self {
         loop {
-            let p = Vec3::random_bounded(-1., 1.);
+            let p = Vec3::random_bounded(rng, -1., 1.);
             if p.len_squared() >= 1. {
                 continue;
             }
@@ -63,8 +63,18 @@ impl Vec3 {
         }
     }
 
-    pub fn random_in_hemisphere(n: &Vec3) -> Self {
-        let in_sphere = Vec3::random_in_unit_sphere();
+    /// Uniform random point in the unit disk of the xy plane.
+    pub fn random_in_unit_disk(rng: &mut Rng) -> Self {
+        loop {
+            let p = Vec3::new(rng.random_range(-1., 1.), rng.random_range(-1., 1.), 0.);
+            if p.len_squared() < 1. {
+                return p;
+            }
+        }
+    }
+
+    pub fn random_in_hemisphere(rng: &mut Rng, n: &Vec3) -> Self {
+        let in_sphere = Vec3::random_in_unit_sphere(rng);
 
         if n.dot(&in_sphere) < 0. {
             return -in_sphere;
@@ -73,38 +83,20 @@ impl Vec3 {
         in_sphere
     }
 
-    pub fn random_unit_vec() -> Self {
-        Vec3::random_in_unit_sphere().unit_vector()
+    pub fn random_unit_vec(rng: &mut Rng) -> Self {
+        Vec3::random_in_unit_sphere(rng).unit_vector()
     }
 
-    pub fn as_colour_string(&self) -> String {
-        let r = self.x / SAMPLES_PER_PIXEL as f64;
-        let g = self.y / SAMPLES_PER_PIXEL as f64;
-        let b = self.z / SAMPLES_PER_PIXEL as f64;
-
-        let scale = 1. / SAMPLES_PER_PIXEL as f64;
-
-        let r = (scale * r).sqrt();
-        let g = (scale * g).sqrt();
-        let b = (scale * b).sqrt();
-
-        let ir = (256. * clamp(r)) as i64;
-        let ig = (256. * clamp(g)) as i64;
-        let ib = (256. * clamp(b)) as i64;
-
-        format!("{ir} {ig} {ib}")
+    /// Average of `samples` summed linear sRGB values, encoded as 8-bit sRGB
+    /// and written as `"r g b"`.
+    pub fn as_colour_string(&self, samples: i64) -> String {
+        let [r, g, b] = to_srgb8(&(*self / samples as f64));
+        format!("{r} {g} {b}")
     }
 
-    pub fn as_colour_bytes(&self) -> Vec<u8> {
-        let r = self.x / SAMPLES_PER_PIXEL as f64;
-        let g = self.y / SAMPLES_PER_PIXEL as f64;
-        let b = self.z / SAMPLES_PER_PIXEL as f64;
-
-        let ir = (256. * clamp(r)) as u8;
-        let ig = (256. * clamp(g)) as u8;
-        let ib = (256. * clamp(b)) as u8;
-
-        vec![ir, ig, ib]
+    /// Average of `samples` summed linear sRGB values, encoded as 8-bit sRGB.
+    pub fn as_colour_bytes(&self, samples: i64) -> Vec<u8> {
+        to_srgb8(&(*self / samples as f64)).to_vec()
     }
 
     pub fn cross(&self, rhs: &Vec3) -> Self {
@@ -127,16 +119,32 @@ impl Vec3 {
         self.x.powi(2) + self.y.powi(2) + self.z.powi(2)
     }
 
+    pub fn max(&self, rhs: &Vec3) -> Self {
+        Self {
+            x: self.x.max(rhs.x),
+            y: self.y.max(rhs.y),
+            z: self.z.max(rhs.z),
+        }
+    }
+
+    pub fn min(&self, rhs: &Vec3) -> Self {
+        Self {
+            x: self.x.min(rhs.x),
+            y: self.y.min(rhs.y),
+            z: self.z.min(rhs.z),
+        }
+    }
+
     pub fn near_zero(&self) -> bool {
         (self.x.abs() < MIN_DIM) && (self.y.abs() < MIN_DIM) && (self.z.abs() < MIN_DIM)
     }
 
-    pub fn new(x: f64, y: f64, z: f64) -> Self {
+    pub const fn new(x: f64, y: f64, z: f64) -> Self {
         Self { x, y, z }
     }
 
     pub fn reflect(&self, normal: &Vec3) -> Self {
-        return *self - 2. * self.dot(normal) * *normal;
+        *self - 2. * self.dot(normal) * *normal
     }
 
     pub fn refract(&self, normal: &Vec3, refraction_ratio: f64) -> Self {
@@ -215,6 +223,19 @@ impl DivAssign<f64> for Vec3 {
     }
 }
 
+impl Index<usize> for Vec3 {
+    type Output = f64;
+
+    fn index(&self, axis: usize) -> &Self::Output {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vec3 axis out of range: {axis}"),
+        }
+    }
+}
+
 impl Mul for Vec3 {
     type Output = Self;
 
@@ -310,9 +331,10 @@ mod tests {
 
     #[test]
     fn as_colour_string() {
-        let r = Vec3::new(0., 1., 0.25);
+        let r = Vec3::new(0., 1., 0.214_041_140_5) * 4.;
 
-        assert_eq!(r.as_colour_string(), "0 255 63\n");
+        assert_eq!(r.as_colour_string(4), "0 255 128");
+        assert_eq!(r.as_colour_bytes(4), [0, 255, 128]);
     }
 
     #[test]
@@ -375,6 +397,27 @@ mod tests {
         assert!(r.len() - 3. < 1e-10)
     }
 
+    #[test]
+    fn index() {
+        let r = Vec3::new(1., 2., 3.);
+
+        assert_eq!(r[0], 1.);
+        assert_eq!(r[1], 2.);
+        assert_eq!(r[2], 3.);
+    }
+
+    #[test]
+    fn min_max() {
+        let l = Vec3::new(1., 5., 3.);
+        let r = Vec3::new(4., 2., 3.);
+
+        let lo = l.min(&r);
+        let hi = l.max(&r);
+
+        assert_eq!((lo.x, lo.y, lo.z), (1., 2., 3.));
+        assert_eq!((hi.x, hi.y, hi.z), (4., 5., 3.));
+    }
+
     #[test]
     fn mul() {
         let l = Vec3::new(1., 2., 3.);
@@ -386,7 +429,7 @@ mod tests {
         assert!(s.y - 4. < 1e-10);
         assert!(s.z - 9. < 1e-10);
 
-        let n = f * s.clone();
+        let n = f * s;
 
         assert!(n.x - 2. < 1e-10);
         assert!(n.y - 8. < 1e-10);
@@ -452,8 +495,8 @@ mod tests {
     fn sum() {
         let v = vec![Vec3::new(1., 2., 3.), Vec3::new(5., 7., 1.)];
         let sum: Vec3 = v.into_iter().sum();
-        assert!(sum.x - 1. + 5. < 1e-10);
-        assert!(sum.y - 2. + 7. < 1e-10);
-        assert!(sum.z - 3. + 1. < 1e-10);
+        assert!(sum.x - (1. + 5.) < 1e-10);
+        assert!(sum.y - (2. + 7.) < 1e-10);
+        assert!(sum.z - (3. + 1.) < 1e-10);
     }
 }
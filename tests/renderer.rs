@@ -0,0 +1,558 @@
+use std::sync::Arc;
+
+use raytracer::{
+    adaptive::AdaptiveSampling,
+    aperture::ApertureShape,
+    background::Background,
+    camera::{Camera, CubeFace, Projection, Stereo, StereoLayout},
+    filter::{FilterKind, PixelFilter},
+    hittable::HittableList,
+    material::{Dielectric, DiffuseLight, Lambertian, Metal},
+    render::Renderer,
+    sampler::SamplerKind,
+    settings::RenderSettings,
+    sphere::{MovingSphere, Sphere},
+    triangle::Triangle,
+    vec3::{Colour, Point, Vec3},
+};
+
+fn tiny_settings() -> RenderSettings {
+    RenderSettings {
+        width: 16,
+        height: 12,
+        samples_per_pixel: 8,
+        max_depth: 4,
+        ..Default::default()
+    }
+}
+
+fn camera(settings: &RenderSettings) -> Camera {
+    Camera::new(
+        &Point::new(0., 0., 3.),
+        &Point::new(0., 0., 0.),
+        &Vec3::new(0., 1., 0.),
+        60.,
+        settings.aspect_ratio(),
+        0.,
+        3.,
+    )
+}
+
+#[test]
+fn renders_tiny_scene() {
+    let mut world = HittableList::new();
+    let black = Arc::new(Lambertian::new(&Colour::new(0., 0., 0.)));
+    world.add(Sphere::new(Point::new(0., 0., 0.), 1., black));
+
+    let settings = tiny_settings();
+    let cam = camera(&settings);
+    let image = Renderer::new(settings).render(&world, &cam);
+
+    assert_eq!(image.width(), 16);
+    assert_eq!(image.height(), 12);
+    assert_eq!(image.pixels().len(), 16 * 12);
+
+    for px in image.pixels() {
+        assert!(px.x.is_finite() && px.y.is_finite() && px.z.is_finite());
+        assert!(px.x >= 0. && px.y >= 0. && px.z >= 0.);
+    }
+
+    // A perfectly absorbing sphere in the middle, sky in the corners.
+    let centre = image.get(8, 6);
+    assert_eq!((centre.x, centre.y, centre.z), (0., 0., 0.));
+
+    let corner = image.get(0, 0);
+    assert!(corner.z > 0.9);
+
+    // Alpha is the sphere's coverage.
+    let alpha = image.alpha().unwrap();
+    assert_eq!((alpha[6 * 16 + 8], alpha[0]), (1., 0.));
+}
+
+#[test]
+fn rows_run_top_to_bottom() {
+    let settings = tiny_settings();
+    let cam = camera(&settings);
+    let image = Renderer::new(settings).render(&HittableList::new(), &cam);
+
+    // The sky gradient is bluer (less red) towards the top of the image.
+    let top = image.get(8, 0);
+    let bottom = image.get(8, 11);
+    assert!(top.x < bottom.x);
+}
+
+#[test]
+fn lights_without_background() {
+    let mut world = HittableList::new();
+    let lamp = Arc::new(DiffuseLight::new(&Colour::new(4., 2., 1.)));
+    world.add(Sphere::new(Point::new(0., 0., 0.), 1., lamp));
+
+    let settings = tiny_settings();
+    let cam = camera(&settings);
+    let mut renderer = Renderer::new(settings);
+    renderer.background = Background::None;
+    let image = renderer.render(&world, &cam);
+
+    let centre = image.get(8, 6);
+    assert_eq!((centre.x, centre.y, centre.z), (4., 2., 1.));
+
+    let corner = image.get(0, 0);
+    assert_eq!((corner.x, corner.y, corner.z), (0., 0., 0.));
+}
+
+#[test]
+fn solid_background() {
+    let settings = tiny_settings();
+    let cam = camera(&settings);
+    let mut renderer = Renderer::new(settings);
+    renderer.background = Background::Solid(Colour::new(0.25, 0.5, 0.75));
+    let image = renderer.render(&HittableList::new(), &cam);
+
+    for px in image.pixels() {
+        assert!((px.x - 0.25).abs() < 1e-12);
+        assert!((px.y - 0.5).abs() < 1e-12);
+        assert!((px.z - 0.75).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn stereo_layouts() {
+    // A light at the convergence distance, filling the left half of the view.
+    let mut world = HittableList::new();
+    let lamp = Arc::new(DiffuseLight::new(&Colour::new(1., 1., 1.)));
+    world.add(Triangle::new(
+        Point::new(0., -100., 0.),
+        Point::new(0., 100., 0.),
+        Point::new(-100., 0., 0.),
+        lamp,
+    ));
+
+    let settings = tiny_settings();
+    let cam = camera(&settings);
+    let mut renderer = Renderer::new(settings);
+    renderer.background = Background::None;
+
+    let stereo = |layout| Stereo {
+        layout,
+        interocular: 0.5,
+        convergence: 3.,
+    };
+
+    let image = renderer.render_stereo(&world, &cam, &stereo(StereoLayout::SideBySide));
+    assert_eq!((image.width(), image.height()), (16, 12));
+    // Both eyes see the edge of the light at the centre of their half.
+    for eye in 0..2 {
+        assert_eq!(image.get(eye * 8 + 2, 6).x, 1.);
+        assert_eq!(image.get(eye * 8 + 5, 6).x, 0.);
+    }
+
+    let image = renderer.render_stereo(&world, &cam, &stereo(StereoLayout::OverUnder));
+    assert_eq!((image.width(), image.height()), (16, 12));
+    for eye in 0..2 {
+        assert_eq!(image.get(4, eye * 6 + 3).x, 1.);
+        assert_eq!(image.get(11, eye * 6 + 3).x, 0.);
+    }
+    assert_eq!(image.alpha().map(|a| a.len()), Some(16 * 12));
+
+    // Odd sizes keep the requested resolution.
+    renderer.settings.width = 15;
+    let image = renderer.render_stereo(&world, &cam, &stereo(StereoLayout::SideBySide));
+    assert_eq!((image.width(), image.height()), (15, 12));
+    renderer.settings.height = 11;
+    let image = renderer.render_stereo(&world, &cam, &stereo(StereoLayout::OverUnder));
+    assert_eq!((image.width(), image.height()), (15, 11));
+}
+
+/// Emissive parallelogram with corners `a`, `b`, `c` and `a + c - b`.
+fn panel(world: &mut HittableList, a: Point, b: Point, c: Point, colour: Colour) {
+    let lamp = Arc::new(DiffuseLight::new(&colour));
+    world.add(Triangle::new(a, b, c, lamp.clone()));
+    world.add(Triangle::new(a, c, a + c - b, lamp));
+}
+
+/// Red far behind the camera on its left and blue on its right, so that a
+/// panorama should be red at its left edge and blue at its right edge.
+fn seam_world() -> HittableList {
+    let mut world = HittableList::new();
+    let (far, z) = (1e6, 1000.);
+    panel(
+        &mut world,
+        Point::new(-far, -far, z),
+        Point::new(0., -far, z),
+        Point::new(0., far, z),
+        Colour::new(1., 0., 0.),
+    );
+    panel(
+        &mut world,
+        Point::new(0., -far, z),
+        Point::new(far, -far, z),
+        Point::new(far, far, z),
+        Colour::new(0., 0., 1.),
+    );
+    world
+}
+
+/// A white ceiling, which lights exactly the upper half of a panorama.
+fn ceiling_world() -> HittableList {
+    let mut world = HittableList::new();
+    let far = 1e4;
+    panel(
+        &mut world,
+        Point::new(-far, 1., -far),
+        Point::new(far, 1., -far),
+        Point::new(far, 1., far),
+        Colour::new(1., 1., 1.),
+    );
+    world
+}
+
+fn panorama_renderer(width: i32, height: i32) -> Renderer {
+    let mut renderer = Renderer::new(RenderSettings {
+        width,
+        height,
+        samples_per_pixel: 16,
+        max_depth: 2,
+        seed: Some(3),
+        ..Default::default()
+    });
+    renderer.background = Background::None;
+    renderer
+}
+
+fn panorama_camera(projection: Projection) -> Camera {
+    Camera::new(
+        &Point::new(0., 0., 0.),
+        &Point::new(0., 0., -1.),
+        &Vec3::new(0., 1., 0.),
+        90.,
+        1.,
+        0.,
+        1.,
+    )
+    .with_projection(projection)
+}
+
+#[test]
+fn equirectangular_panoramas_span_the_sphere() {
+    let renderer = panorama_renderer(8, 4);
+    let cam = panorama_camera(Projection::Equirectangular);
+
+    // The edge columns end at -180 and 180 degrees rather than wrapping.
+    let image = renderer.render(&seam_world(), &cam);
+    for y in 1..3 {
+        let (left, right) = (image.get(0, y), image.get(7, y));
+        assert!(left.x > 0.99 && left.z == 0., "{left}");
+        assert!(right.z > 0.99 && right.x == 0., "{right}");
+    }
+
+    // The horizon falls between the middle rows.
+    let image = renderer.render(&ceiling_world(), &cam);
+    for x in 0..8 {
+        assert!(image.get(x, 0).x > 0.99);
+        assert!(image.get(x, 1).x > 0.);
+        assert_eq!((image.get(x, 2).x, image.get(x, 3).x), (0., 0.));
+    }
+}
+
+#[test]
+fn omnidirectional_stereo_panoramas() {
+    let renderer = panorama_renderer(8, 8);
+    let cam = panorama_camera(Projection::Equirectangular);
+    let stereo = Stereo {
+        layout: StereoLayout::OverUnder,
+        interocular: 0.064,
+        convergence: f64::INFINITY,
+    };
+
+    // Each eye's panorama has the same edges and horizon as a mono one.
+    let image = renderer.render_stereo(&seam_world(), &cam, &stereo);
+    for y in [1, 2, 5, 6] {
+        let (left, right) = (image.get(0, y), image.get(7, y));
+        assert!(left.x > 0.9 && left.z < 0.1, "{left}");
+        assert!(right.z > 0.9 && right.x < 0.1, "{right}");
+    }
+
+    let image = renderer.render_stereo(&ceiling_world(), &cam, &stereo);
+    for eye in [0, 4] {
+        for x in 0..8 {
+            assert!(image.get(x, eye).x > 0.99);
+            assert!(image.get(x, eye + 1).x > 0.);
+            assert_eq!((image.get(x, eye + 2).x, image.get(x, eye + 3).x), (0., 0.));
+        }
+    }
+}
+
+#[test]
+fn cube_faces_meet_at_their_edges() {
+    // A light filling the right face of the cube around the camera exactly.
+    let mut world = HittableList::new();
+    panel(
+        &mut world,
+        Point::new(1., -1., -1.),
+        Point::new(1., -1., 1.),
+        Point::new(1., 1., 1.),
+        Colour::new(1., 1., 1.),
+    );
+
+    let renderer = panorama_renderer(8, 8);
+    let face = |face| renderer.render(&world, &panorama_camera(Projection::CubeFace { face }));
+    assert!(face(CubeFace::Right).pixels().iter().all(|px| px.x > 0.99));
+    for other in [
+        CubeFace::Front,
+        CubeFace::Back,
+        CubeFace::Up,
+        CubeFace::Down,
+    ] {
+        assert!(
+            face(other).pixels().iter().all(|px| px.x == 0.),
+            "{other:?}"
+        );
+    }
+}
+
+#[test]
+fn motion_blur() {
+    // A light sweeping from left to right while the shutter is open.
+    let mut world = HittableList::new();
+    let lamp = Arc::new(DiffuseLight::new(&Colour::new(1., 1., 1.)));
+    world.add(MovingSphere::new(
+        Point::new(-1., 0., 0.),
+        0.,
+        Point::new(1., 0., 0.),
+        1.,
+        0.3,
+        lamp,
+    ));
+
+    let settings = RenderSettings {
+        samples_per_pixel: 64,
+        ..tiny_settings()
+    };
+    let mut renderer = Renderer::new(settings.clone());
+    renderer.background = Background::None;
+
+    let still = renderer.render(&world, &camera(&settings));
+    assert_eq!(still.get(8, 6).x, 0.);
+
+    let cam = camera(&settings).with_shutter(0., 1.);
+    let blurred = renderer.render(&world, &cam);
+    let streak = blurred.get(8, 6).x;
+    assert!(streak > 0.05 && streak < 0.6, "{streak}");
+}
+
+/// Renders a small light far out of focus, so it spreads into an image of
+/// the aperture. Returns the fraction of the silhouette's bounding box that
+/// is lit and the box's width and height in pixels.
+fn bokeh(shape: ApertureShape, squeeze: f64) -> (f64, usize, usize) {
+    let mut world = HittableList::new();
+    let lamp = Arc::new(DiffuseLight::new(&Colour::new(1., 1., 1.)));
+    world.add(Sphere::new(Point::new(0., 0., -1.), 0.06, lamp));
+
+    let settings = RenderSettings {
+        width: 25,
+        height: 25,
+        samples_per_pixel: 256,
+        max_depth: 1,
+        ..Default::default()
+    };
+    let cam = Camera::new(
+        &Point::new(0., 0., 0.),
+        &Point::new(0., 0., -1.),
+        &Vec3::new(0., 1., 0.),
+        60.,
+        1.,
+        1.,
+        20.,
+    )
+    .with_aperture_shape(shape)
+    .with_anamorphic_squeeze(squeeze);
+
+    let mut renderer = Renderer::new(settings);
+    renderer.background = Background::None;
+    let image = renderer.render(&world, &cam);
+
+    let lit: Vec<(usize, usize)> = (0..image.height())
+        .flat_map(|y| (0..image.width()).map(move |x| (x, y)))
+        .filter(|&(x, y)| image.get(x, y).x > 0.)
+        .collect();
+    let width = lit.iter().map(|p| p.0).max().unwrap() - lit.iter().map(|p| p.0).min().unwrap() + 1;
+    let height =
+        lit.iter().map(|p| p.1).max().unwrap() - lit.iter().map(|p| p.1).min().unwrap() + 1;
+
+    (lit.len() as f64 / (width * height) as f64, width, height)
+}
+
+#[test]
+fn bokeh_silhouette() {
+    let polygon = |rotation| ApertureShape::Polygon {
+        blades: 4,
+        rotation,
+        rounding: 0.,
+    };
+
+    // A square fills its bounding box, a diamond only half of it. The light's
+    // own size softens the corners a little.
+    let (fill, width, height) = bokeh(polygon(45.), 1.);
+    assert!(fill > 0.7, "{fill}");
+    assert!(
+        width.abs_diff(height) <= 3 && width > 12,
+        "{width}x{height}"
+    );
+
+    let (fill, ..) = bokeh(polygon(0.), 1.);
+    assert!(fill < 0.6, "{fill}");
+
+    // Anamorphic squeeze turns circles into tall ovals.
+    let (fill, width, height) = bokeh(ApertureShape::Circle, 2.);
+    assert!((0.6..0.9).contains(&fill), "{fill}");
+    assert!((width as f64) < 0.7 * height as f64, "{width}x{height}");
+}
+
+#[test]
+fn seeded_renders_are_bit_identical() {
+    let mut world = HittableList::new();
+    let grey = Arc::new(Lambertian::new(&Colour::new(0.5, 0.5, 0.5)));
+    let metal = Arc::new(Metal::new(&Colour::new(0.8, 0.6, 0.2), 0.3));
+    world.add(Sphere::new(Point::new(0., -100.5, 0.), 100., grey));
+    world.add(Sphere::new(Point::new(-0.6, 0., 0.), 0.5, metal));
+    world.add(Sphere::new(
+        Point::new(0.6, 0., 0.),
+        0.5,
+        Arc::new(Dielectric::new(1.5)),
+    ));
+
+    let settings = RenderSettings {
+        seed: Some(99),
+        ..tiny_settings()
+    };
+    let cam = Camera::new(
+        &Point::new(0., 0.5, 3.),
+        &Point::new(0., 0., 0.),
+        &Vec3::new(0., 1., 0.),
+        60.,
+        settings.aspect_ratio(),
+        0.2,
+        3.,
+    )
+    .with_shutter(0., 0.5);
+
+    // Whatever the number of threads, every pixel gets the same samples.
+    let render = |threads, settings: &RenderSettings| {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        let renderer = Renderer::new(settings.clone());
+        let image = pool.install(|| renderer.render(&world, &cam));
+        image
+            .pixels()
+            .iter()
+            .flat_map(|c| [c.x, c.y, c.z])
+            .map(f64::to_bits)
+            .collect::<Vec<u64>>()
+    };
+    let single = render(1, &settings);
+    assert_eq!(single, render(4, &settings));
+    assert_eq!(single, render(3, &settings));
+
+    let other = RenderSettings {
+        seed: Some(100),
+        ..settings.clone()
+    };
+    assert_ne!(single, render(4, &other));
+
+    let sobol = RenderSettings {
+        sampler: SamplerKind::Sobol,
+        ..settings.clone()
+    };
+    assert_eq!(render(1, &sobol), render(4, &sobol));
+
+    // Wide filters splat onto neighbouring pixels, still in a fixed order.
+    let mitchell = RenderSettings {
+        filter: PixelFilter::new(FilterKind::Mitchell),
+        ..settings.clone()
+    };
+    let single = render(1, &mitchell);
+    assert_eq!(single, render(4, &mitchell));
+    assert_eq!(single, render(3, &mitchell));
+}
+
+#[test]
+fn reconstruction_filters_soften_edges() {
+    let mut world = HittableList::new();
+    let black = Arc::new(Lambertian::new(&Colour::new(0., 0., 0.)));
+    world.add(Sphere::new(Point::new(0., 0., 0.), 1., black));
+
+    let render = |filter| {
+        let settings = RenderSettings {
+            samples_per_pixel: 64,
+            filter,
+            ..tiny_settings()
+        };
+        let mut renderer = Renderer::new(settings.clone());
+        renderer.background = Background::Solid(Colour::new(1., 1., 1.));
+        renderer.render(&world, &camera(&settings))
+    };
+    // Sum of squared steps between horizontally neighbouring pixels.
+    let steps = |image: &raytracer::framebuffer::Framebuffer| {
+        let mut sum = 0.;
+        for y in 0..image.height() {
+            for x in 1..image.width() {
+                sum += (image.get(x, y).y - image.get(x - 1, y).y).powi(2);
+            }
+        }
+        sum
+    };
+
+    let sharp = render(PixelFilter::default());
+    let soft = render(PixelFilter::new(FilterKind::Gaussian).with_radius(2.));
+    assert!(steps(&soft) < 0.7 * steps(&sharp));
+
+    // Far from the edge the filter makes little difference.
+    for image in [&sharp, &soft] {
+        assert!(image.get(8, 6).y < 1e-3);
+        assert_eq!(image.get(0, 0).y, 1.);
+    }
+}
+
+#[test]
+fn adaptive_sampling_spends_samples_on_noise() {
+    let mut world = HittableList::new();
+    let grey = Arc::new(Lambertian::new(&Colour::new(0.5, 0.5, 0.5)));
+    world.add(Sphere::new(Point::new(0., 0., 0.), 1., grey));
+
+    let settings = RenderSettings {
+        seed: Some(5),
+        adaptive: Some(AdaptiveSampling::new(0.02).with_max_samples(64)),
+        ..tiny_settings()
+    };
+    let cam = camera(&settings);
+    let render = |threads| {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        pool.install(|| Renderer::new(settings.clone()).render(&world, &cam))
+    };
+    let image = render(4);
+    let counts = image.sample_counts().unwrap();
+
+    // The smooth sky settles after the first pass, the diffuse sphere does
+    // not, and no pixel goes past the maximum.
+    assert_eq!(counts[0], 8);
+    assert!(counts[6 * 16 + 8] > 8);
+    assert!(counts.iter().all(|&n| (8..=64).contains(&n)));
+
+    // Which pixels get more samples does not depend on the threads either.
+    let single = render(1);
+    assert_eq!(single.sample_counts(), image.sample_counts());
+    let bits = |image: &raytracer::framebuffer::Framebuffer| {
+        let pixels = image.pixels().iter();
+        pixels.map(|c| c.y.to_bits()).collect::<Vec<u64>>()
+    };
+    assert_eq!(bits(&single), bits(&image));
+
+    // Without adaptive sampling every pixel takes the same number.
+    let uniform = Renderer::new(tiny_settings()).render(&world, &cam);
+    assert!(uniform.sample_counts().unwrap().iter().all(|&n| n == 8));
+}